@@ -1,4 +1,4 @@
-use chess_move_validator::{Square, UniquePiece, ChessError};
+use chess_move_validator::{Square, UniquePiece, ChessError, Color};
 
 fn main() -> Result<(), ChessError> {
     let mut board = chess_move_validator::Board::new();
@@ -12,10 +12,10 @@ fn main() -> Result<(), ChessError> {
     // This should capture the black pawn on D5
     board.simple_move(&Square::E4, &Square::D5, None)?;
 
-    let mut cursor = board.position_cursor();
+    let cursor = board.position_cursor();
     let pos = cursor.curr();
-    assert_eq!(pos.get(&(UniquePiece::EPawn, true)).unwrap(), &Square::D5);
-    assert!(pos.get(&(UniquePiece::DPawn, false)).is_none());
+    assert_eq!(pos.get(&(UniquePiece::EPawn, Color::White)).unwrap(), &Square::D5);
+    assert!(!pos.contains_key(&(UniquePiece::DPawn, Color::Black)));
     Ok(())
 
 }
\ No newline at end of file
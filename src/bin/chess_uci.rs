@@ -0,0 +1,304 @@
+// A chess engine speaking the UCI protocol over stdin and stdout, searching with
+// `Board::search`. Given a depth limit it always answers the same position the same way.
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chess_move_validator::{Board, ChessError, Color, SearchLimits, SearchResult, MATE_SCORE};
+
+// Moves left in the game assumed when the GUI doesn't say
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+struct Engine {
+    board: Board,
+    chess960: bool,
+    search: Option<(Arc<AtomicBool>, JoinHandle<()>)>,
+}
+
+impl Engine {
+    fn new() -> Engine {
+        Engine {
+            board: Board::new(),
+            chess960: false,
+            search: None,
+        }
+    }
+
+    // Returns false once the GUI asks to quit
+    fn handle(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.split_first() {
+            Some((&"uci", _)) => {
+                println!(
+                    "id name {} {}",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                );
+                println!("id author {}", env!("CARGO_PKG_AUTHORS"));
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            }
+            Some((&"isready", _)) => println!("readyok"),
+            Some((&"setoption", ["name", "UCI_Chess960", "value", value])) => {
+                self.chess960 = *value == "true";
+                if let Err(error) = self.board.set_chess960(self.chess960) {
+                    println!("info string {}", error);
+                }
+            }
+            Some((&"ucinewgame", _)) => {
+                self.stop_search();
+                self.board = Board::new();
+                if let Err(error) = self.board.set_chess960(self.chess960) {
+                    println!("info string {}", error);
+                }
+            }
+            Some((&"position", args)) => {
+                self.stop_search();
+                match parse_position(args, self.chess960) {
+                    Ok(board) => self.board = board,
+                    Err(error) => println!("info string {}", error),
+                }
+            }
+            Some((&"go", args)) => {
+                self.stop_search();
+                let limits = parse_go(args, self.board.side_to_move());
+                self.start_search(limits, args.contains(&"infinite"));
+            }
+            Some((&"stop", _)) => self.stop_search(),
+            Some((&"quit", _)) => {
+                self.stop_search();
+                return false;
+            }
+            // Unknown commands are ignored, as the protocol asks
+            _ => {}
+        }
+        true
+    }
+
+    fn start_search(&mut self, limits: SearchLimits, infinite: bool) {
+        let stop = limits.stop.clone();
+        let board = self.board.clone();
+        let handle = thread::spawn(move || {
+            run_search(&board, &limits, infinite, |line| println!("{}", line));
+        });
+        self.search = Some((stop, handle));
+    }
+
+    // Ends the running search, if any, once it has sent its best move
+    fn stop_search(&mut self) {
+        if let Some((stop, handle)) = self.search.take() {
+            stop.store(true, Ordering::Relaxed);
+            handle.thread().unpark();
+            handle.join().expect("search thread panicked");
+        }
+    }
+}
+
+// Searches and sends an info line per iteration, then the best move. An infinite search holds
+// back its best move until it is told to stop, even if it finished early, as the protocol asks.
+fn run_search<F: FnMut(String)>(board: &Board, limits: &SearchLimits, infinite: bool, mut send: F) {
+    let result = board.search_with(limits, |result| send(info_line(board, result)));
+    while infinite && !limits.stop.load(Ordering::Relaxed) {
+        thread::park();
+    }
+    let best_move = result
+        .best_move
+        .and_then(|m| board.to_uci(&m.from(), &m.to(), m.promotion()).ok());
+    send(format!(
+        "bestmove {}",
+        best_move.as_deref().unwrap_or("0000")
+    ));
+}
+
+// Reads "startpos" or "fen <fields>", optionally followed by "moves" and moves in UCI notation
+fn parse_position(args: &[&str], chess960: bool) -> Result<Board, ChessError> {
+    let moves_index = args
+        .iter()
+        .position(|arg| *arg == "moves")
+        .unwrap_or(args.len());
+    let mut board = match args[..moves_index] {
+        ["startpos"] => Board::new(),
+        ["fen", ref fen @ ..] => Board::from_fen(&fen.join(" "))?,
+        _ => {
+            return Err(ChessError::FenParseError(
+                "expected startpos or fen".to_string(),
+            ))
+        }
+    };
+    if chess960 {
+        board.set_chess960(true)?;
+    }
+    for uci in args.iter().skip(moves_index + 1) {
+        board.apply_uci(uci)?;
+    }
+    Ok(board)
+}
+
+fn parse_go(args: &[&str], side_to_move: Color) -> SearchLimits {
+    let value = |name: &str| -> Option<u64> {
+        let index = args.iter().position(|arg| *arg == name)?;
+        args.get(index + 1)?.parse().ok()
+    };
+    let (time_left, increment) = if side_to_move == Color::White {
+        (value("wtime"), value("winc"))
+    } else {
+        (value("btime"), value("binc"))
+    };
+    // A share of the clock plus most of the increment, keeping half of the clock in reserve
+    let clock_time = time_left.map(|time_left| {
+        let moves_to_go = value("movestogo").unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        (time_left / moves_to_go + increment.unwrap_or(0) * 3 / 4).min(time_left / 2)
+    });
+    SearchLimits {
+        depth: value("depth").map(|depth| depth as u32),
+        time: value("movetime").or(clock_time).map(Duration::from_millis),
+        stop: Arc::new(AtomicBool::new(false)),
+    }
+}
+
+fn info_line(board: &Board, result: &SearchResult) -> String {
+    let score = if result.score.abs() >= MATE_SCORE - 1000 {
+        let moves = (MATE_SCORE - result.score.abs() + 1) / 2;
+        format!("mate {}", result.score.signum() * moves)
+    } else {
+        format!("cp {}", result.score)
+    };
+    let mut line = format!(
+        "info depth {} score {} nodes {}",
+        result.depth, score, result.nodes
+    );
+    // Each move is written in the position it's played from, for castling's sake
+    let mut board = board.clone();
+    let mut pv = Vec::new();
+    for m in result.pv.iter() {
+        match board.to_uci(&m.from(), &m.to(), m.promotion()) {
+            Ok(uci) if board.apply_move(m).is_ok() => pv.push(uci),
+            _ => break,
+        }
+    }
+    if !pv.is_empty() {
+        line.push_str(&format!(" pv {}", pv.join(" ")));
+    }
+    line
+}
+
+fn main() {
+    let mut engine = Engine::new();
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) if engine.handle(&line) => {}
+            _ => break,
+        }
+    }
+    engine.stop_search();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{info_line, parse_go, parse_position, run_search};
+    use chess_move_validator::{Board, ChessError, Color, SearchResult, MATE_SCORE};
+
+    #[test]
+    fn test_parse_position() -> Result<(), ChessError> {
+        let board = parse_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3"], false)?;
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        let args = "fen 4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1 moves e1h1".split(' ');
+        let board = parse_position(&args.collect::<Vec<_>>(), true)?;
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+        assert!(board.is_chess960());
+
+        assert!(parse_position(&["startpos", "moves", "e2e5"], false).is_err());
+        assert!(parse_position(&["fen"], false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_go() {
+        let limits = parse_go(&["depth", "4"], Color::White);
+        assert_eq!(limits.depth, Some(4));
+        assert_eq!(limits.time, None);
+
+        let limits = parse_go(&["movetime", "250"], Color::White);
+        assert_eq!(limits.time, Some(Duration::from_millis(250)));
+
+        let args = [
+            "wtime", "60000", "btime", "1000", "winc", "400", "binc", "400",
+        ];
+        assert_eq!(
+            parse_go(&args, Color::White).time,
+            Some(Duration::from_millis(2_300))
+        );
+        assert_eq!(
+            parse_go(&args, Color::Black).time,
+            Some(Duration::from_millis(333))
+        );
+        assert_eq!(parse_go(&["infinite"], Color::White).time, None);
+    }
+
+    #[test]
+    fn test_infinite_search_waits_for_stop() -> Result<(), ChessError> {
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")?;
+        let mut lines = Vec::new();
+        run_search(&board, &parse_go(&[], Color::White), false, |line| {
+            lines.push(line)
+        });
+        assert_eq!(lines.last().map(String::as_str), Some("bestmove a1a8"));
+
+        // The mate is found at once, but the best move waits for stop
+        let limits = parse_go(&["infinite"], Color::White);
+        let stop = limits.stop.clone();
+        let (sender, receiver) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            run_search(&board, &limits, true, |line| sender.send(line).unwrap());
+        });
+        let timeout = Duration::from_millis(200);
+        assert!(receiver
+            .recv_timeout(timeout)
+            .unwrap()
+            .starts_with("info depth 1"));
+        assert!(receiver.recv_timeout(timeout).is_err());
+        stop.store(true, Ordering::Relaxed);
+        handle.thread().unpark();
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+            "bestmove a1a8"
+        );
+        handle.join().unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_info_line() {
+        let mut board = Board::new();
+        let e4 = board.from_uci("e2e4").unwrap();
+        board.apply_move(&e4).unwrap();
+        let e5 = board.from_uci("e7e5").unwrap();
+        board.undo();
+        let mut result = SearchResult {
+            best_move: Some(e4),
+            pv: vec![e4, e5],
+            score: 35,
+            depth: 3,
+            nodes: 1234,
+        };
+        assert_eq!(
+            info_line(&board, &result),
+            "info depth 3 score cp 35 nodes 1234 pv e2e4 e7e5"
+        );
+        result.score = MATE_SCORE - 3;
+        assert!(info_line(&board, &result).contains("score mate 2"));
+        result.score = 4 - MATE_SCORE;
+        assert!(info_line(&board, &result).contains("score mate -2"));
+    }
+}
@@ -0,0 +1,185 @@
+// Replays every game of the given PGN files and reports the moves that can't be played.
+// Exits with 1 if any game failed and with 2 on usage or file errors.
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+
+use chess_move_validator::{PgnReadError, PgnReader};
+
+const USAGE: &str = "Usage: chess-validate [--json] FILE...";
+
+// A game that failed to replay, numbered from 1 within its file
+struct Failure {
+    file: String,
+    game: usize,
+    ply: Option<usize>,
+    san: Option<String>,
+    reason: String,
+}
+
+impl Failure {
+    fn new(file: &str, error: &PgnReadError) -> Failure {
+        Failure {
+            file: file.to_string(),
+            game: error.game_index + 1,
+            ply: error.ply,
+            san: error.san.clone(),
+            reason: error.error.to_string(),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = format!("{}: game {}", self.file, self.game);
+        if let (Some(ply), Some(san)) = (self.ply, &self.san) {
+            text.push_str(&format!(", ply {} ({})", ply, san));
+        }
+        format!("{}: {}", text, self.reason)
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"file\":{},\"game\":{},\"ply\":{},\"san\":{},\"reason\":{}}}",
+            json_string(&self.file),
+            self.game,
+            self.ply.map_or("null".to_string(), |ply| ply.to_string()),
+            self.san.as_deref().map_or("null".to_string(), json_string),
+            json_string(&self.reason)
+        )
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// Number of games read and the ones that failed
+fn validate_file(path: &str) -> std::io::Result<(usize, Vec<Failure>)> {
+    Ok(validate_games(path, File::open(path)?))
+}
+
+fn validate_games<R: Read>(file: &str, reader: R) -> (usize, Vec<Failure>) {
+    let mut games = 0;
+    let mut failures = Vec::new();
+    for result in PgnReader::new(reader) {
+        games += 1;
+        if let Err(error) = result {
+            failures.push(Failure::new(file, &error));
+        }
+    }
+    (games, failures)
+}
+
+fn main() {
+    let mut json = false;
+    let mut paths = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("Unknown option {}\n{}", arg, USAGE);
+                process::exit(2);
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let mut games = 0;
+    let mut failures = Vec::new();
+    for path in paths.iter() {
+        match validate_file(path) {
+            Ok((file_games, file_failures)) => {
+                games += file_games;
+                failures.extend(file_failures);
+            }
+            Err(error) => {
+                eprintln!("Failed to read {}: {}", path, error);
+                process::exit(2);
+            }
+        }
+    }
+
+    if json {
+        let failures: Vec<String> = failures.iter().map(Failure::to_json).collect();
+        println!(
+            "{{\"games\":{},\"failures\":[{}]}}",
+            games,
+            failures.join(",")
+        );
+    } else {
+        for failure in failures.iter() {
+            println!("{}", failure.to_text());
+        }
+        println!("{} games checked, {} failed", games, failures.len());
+    }
+    if !failures.is_empty() {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{json_string, validate_games, Failure};
+
+    #[test]
+    fn test_failure_output() {
+        let failure = Failure {
+            file: "round 1.pgn".to_string(),
+            game: 3,
+            ply: Some(12),
+            san: Some("Nd2".to_string()),
+            reason: "Unable to disambiguate 'from' square".to_string(),
+        };
+        assert_eq!(
+            failure.to_text(),
+            "round 1.pgn: game 3, ply 12 (Nd2): Unable to disambiguate 'from' square"
+        );
+        assert_eq!(
+            failure.to_json(),
+            "{\"file\":\"round 1.pgn\",\"game\":3,\"ply\":12,\"san\":\"Nd2\",\
+             \"reason\":\"Unable to disambiguate 'from' square\"}"
+        );
+    }
+
+    #[test]
+    fn test_over_qualified_moves() {
+        let pgn = "[Event \"One\"]\n\n1. e4 d5 2. ed5 Qxd5 3. Ng1f3 Qd8 4. Nbc3 *\n\n\
+                   [Event \"Two\"]\n\n1. Ngf3 e5 2. Nh1f3 *\n";
+        let (games, failures) = validate_games("round 1.pgn", pgn.as_bytes());
+        assert_eq!(games, 2);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(
+            failures[0].to_text(),
+            "round 1.pgn: game 2, ply 3 (Nh1f3): No legal move matches 'Nh1f3'"
+        );
+    }
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(
+            json_string("a \"b\"\\\n\u{1}"),
+            "\"a \\\"b\\\"\\\\\\n\\u0001\""
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::cell::Ref;
+use std::sync::OnceLock;
+
+use chess_pgn_parser::Square;
+
+use crate::piece_data::PieceData;
+use crate::square_index;
+use crate::{rules, zobrist, Board, Color, PieceKind, UniquePiece};
+
+// One bit per square, a1 = bit 0, b1 = bit 1, ..., h8 = bit 63
+pub(crate) type Bitboard = u64;
+
+// Ray directions as (file, rank) steps. The first four run towards higher square indices.
+const DIRECTIONS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (-1, 1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (1, -1),
+];
+const STRAIGHT_DIRECTIONS: [usize; 4] = [0, 2, 4, 6];
+const DIAGONAL_DIRECTIONS: [usize; 4] = [1, 3, 5, 7];
+
+struct AttackTables {
+    knight: [Bitboard; 64],
+    king: [Bitboard; 64],
+    // Indexed by [color index][square]
+    pawn: [[Bitboard; 64]; 2],
+    rays: [[Bitboard; 64]; 8],
+}
+
+fn tables() -> &'static AttackTables {
+    static TABLES: OnceLock<AttackTables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let steps = |index: usize, offsets: &[(i32, i32)]| {
+            let (file, rank) = ((index % 8) as i32, (index / 8) as i32);
+            offsets
+                .iter()
+                .map(|(file_offset, rank_offset)| (file + file_offset, rank + rank_offset))
+                .filter(|(file, rank)| (0..8).contains(file) && (0..8).contains(rank))
+                .fold(0, |bitboard, (file, rank)| {
+                    bitboard | 1 << (rank * 8 + file)
+                })
+        };
+        let mut tables = AttackTables {
+            knight: [0; 64],
+            king: [0; 64],
+            pawn: [[0; 64]; 2],
+            rays: [[0; 64]; 8],
+        };
+        for index in 0..64 {
+            tables.knight[index] = steps(
+                index,
+                &[
+                    (1, 2),
+                    (2, 1),
+                    (2, -1),
+                    (1, -2),
+                    (-1, -2),
+                    (-2, -1),
+                    (-2, 1),
+                    (-1, 2),
+                ],
+            );
+            tables.king[index] = steps(index, &DIRECTIONS);
+            tables.pawn[Color::White.index()][index] = steps(index, &[(1, 1), (-1, 1)]);
+            tables.pawn[Color::Black.index()][index] = steps(index, &[(1, -1), (-1, -1)]);
+            for (direction, (file_offset, rank_offset)) in DIRECTIONS.iter().enumerate() {
+                let mut ray = Vec::new();
+                for distance in 1..8 {
+                    ray.push((file_offset * distance, rank_offset * distance));
+                }
+                tables.rays[direction][index] = steps(index, &ray);
+            }
+        }
+        tables
+    })
+}
+
+// Squares reached along the given directions, stopping at and including the first occupied
+// square of each ray
+fn slider_attacks(index: usize, occupied: Bitboard, directions: &[usize]) -> Bitboard {
+    let rays = &tables().rays;
+    let mut attacks = 0;
+    for direction in directions {
+        let ray = rays[*direction][index];
+        let blockers = ray & occupied;
+        attacks |= if blockers == 0 {
+            ray
+        } else if *direction < 4 {
+            ray ^ rays[*direction][blockers.trailing_zeros() as usize]
+        } else {
+            ray ^ rays[*direction][63 - blockers.leading_zeros() as usize]
+        };
+    }
+    attacks
+}
+
+pub(crate) fn knight_attacks(index: usize) -> Bitboard {
+    tables().knight[index]
+}
+
+pub(crate) fn king_attacks(index: usize) -> Bitboard {
+    tables().king[index]
+}
+
+// Squares a pawn of the given colour on `index` captures on
+pub(crate) fn pawn_attacks(color: Color, index: usize) -> Bitboard {
+    tables().pawn[color.index()][index]
+}
+
+pub(crate) fn bishop_attacks(index: usize, occupied: Bitboard) -> Bitboard {
+    slider_attacks(index, occupied, &DIAGONAL_DIRECTIONS)
+}
+
+pub(crate) fn rook_attacks(index: usize, occupied: Bitboard) -> Bitboard {
+    slider_attacks(index, occupied, &STRAIGHT_DIRECTIONS)
+}
+
+pub(crate) fn square_index(square: &Square) -> Option<usize> {
+    let (file, rank) = square_index::square_coords(square)?;
+    Some((rank * 8 + file) as usize)
+}
+
+// The squares from one index to another on the same rank, both included
+pub(crate) fn rank_span(from: usize, to: usize) -> Bitboard {
+    let (low, high) = (from.min(to), from.max(to));
+    (u64::MAX >> (63 - high)) & (u64::MAX << low)
+}
+
+pub(crate) fn to_indices(mut bitboard: Bitboard) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let index = bitboard.trailing_zeros() as usize;
+        bitboard &= bitboard - 1;
+        Some(index)
+    })
+}
+
+pub(crate) fn index_square(index: usize) -> Square {
+    square_index::square_at((index % 8) as i32, (index / 8) as i32).unwrap()
+}
+
+pub(crate) fn to_squares(mut bitboard: Bitboard) -> Vec<Square> {
+    let mut squares = Vec::with_capacity(bitboard.count_ones() as usize);
+    while bitboard != 0 {
+        squares.push(index_square(bitboard.trailing_zeros() as usize));
+        bitboard &= bitboard - 1;
+    }
+    squares
+}
+
+// Piece placement as one bitboard per piece type and colour, plus a square to piece lookup
+// into `Board::pieces`
+#[derive(Clone)]
+pub(crate) struct Bitboards {
+    // Indexed by [color index][piece kind index]
+    pieces: [[Bitboard; 6]; 2],
+    colors: [Bitboard; 2],
+    mailbox: [Option<u8>; 64],
+}
+
+impl Bitboards {
+    pub fn empty() -> Bitboards {
+        Bitboards {
+            pieces: [[0; 6]; 2],
+            colors: [0; 2],
+            mailbox: [None; 64],
+        }
+    }
+
+    pub fn from_pieces(pieces: &[PieceData]) -> Bitboards {
+        let mut bitboards = Self::empty();
+        for (piece_data_index, piece_data) in pieces.iter().enumerate() {
+            if let Some(index) = piece_data.curr_square().and_then(square_index) {
+                bitboards.add(
+                    piece_data_index,
+                    piece_data.behavior.kind(),
+                    piece_data.color,
+                    index,
+                );
+            }
+        }
+        bitboards
+    }
+
+    pub fn add(&mut self, piece_data_index: usize, piece: PieceKind, color: Color, index: usize) {
+        self.pieces[color.index()][piece.index()] |= 1 << index;
+        self.colors[color.index()] |= 1 << index;
+        self.mailbox[index] = Some(piece_data_index as u8);
+    }
+
+    pub fn remove(&mut self, piece: PieceKind, color: Color, index: usize) {
+        self.pieces[color.index()][piece.index()] &= !(1 << index);
+        self.colors[color.index()] &= !(1 << index);
+        self.mailbox[index] = None;
+    }
+
+    // Index into `Board::pieces` of the piece on the square
+    pub fn at(&self, index: usize) -> Option<usize> {
+        self.mailbox[index].map(usize::from)
+    }
+
+    pub fn pieces(&self, piece: PieceKind, color: Color) -> Bitboard {
+        self.pieces[color.index()][piece.index()]
+    }
+
+    pub fn color(&self, color: Color) -> Bitboard {
+        self.colors[color.index()]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    // Pieces of the given colour attacking the square, given the occupancy and ignoring
+    // the pieces in `ignored`, as when testing a move before playing it
+    pub fn attackers(
+        &self,
+        index: usize,
+        by_color: Color,
+        occupied: Bitboard,
+        ignored: Bitboard,
+    ) -> Bitboard {
+        let pieces = |piece| self.pieces(piece, by_color) & !ignored;
+        let diagonal = pieces(PieceKind::Bishop) | pieces(PieceKind::Queen);
+        let straight = pieces(PieceKind::Rook) | pieces(PieceKind::Queen);
+        (pawn_attacks(!by_color, index) & pieces(PieceKind::Pawn))
+            | (knight_attacks(index) & pieces(PieceKind::Knight))
+            | (king_attacks(index) & pieces(PieceKind::King))
+            | (bishop_attacks(index, occupied) & diagonal)
+            | (rook_attacks(index, occupied) & straight)
+    }
+}
+
+// Placement changes go through these so the bitboards stay in step with `Board::pieces`
+impl Board {
+    // The bitboards, first rebuilt if pieces were changed through `get_mut_piece_data_at_square`
+    // The Zobrist hash is rebuilt along with them.
+    pub(crate) fn bitboards(&self) -> Ref<'_, Bitboards> {
+        if self.stale_bitboards.get() {
+            let bitboards = Bitboards::from_pieces(&self.pieces);
+            let placement_hash = zobrist::placement_hash(&bitboards);
+            *self.bitboards.borrow_mut() = bitboards;
+            self.stale_bitboards.set(false);
+            self.hash.set(placement_hash ^ self.state_hash());
+        }
+        self.bitboards.borrow()
+    }
+
+    fn bitboards_mut(&mut self) -> &mut Bitboards {
+        drop(self.bitboards());
+        self.bitboards.get_mut()
+    }
+
+    fn toggle_piece_hash(&mut self, piece: PieceKind, color: Color, index: usize) {
+        let hash = self.hash.get_mut();
+        *hash ^= zobrist::piece_key(piece, color, index);
+    }
+
+    // Moves the piece on `from` to the empty square `to`
+    pub(crate) fn move_piece(&mut self, from: &Square, to: &Square) -> Option<()> {
+        let (from_index, to_index) = (square_index(from)?, square_index(to)?);
+        let piece_data_index = self.bitboards_mut().at(from_index)?;
+        let piece_data = &mut self.pieces[piece_data_index];
+        let (piece, color) = (piece_data.behavior.kind(), piece_data.color);
+        piece_data.move_unchecked(to.clone());
+        let bitboards = self.bitboards_mut();
+        bitboards.remove(piece, color, from_index);
+        bitboards.add(piece_data_index, piece, color, to_index);
+        self.toggle_piece_hash(piece, color, from_index);
+        self.toggle_piece_hash(piece, color, to_index);
+        Some(())
+    }
+
+    // Takes the piece on the square off the board and returns who it was
+    pub(crate) fn remove_piece(&mut self, square: &Square) -> Option<UniquePiece> {
+        let index = square_index(square)?;
+        let piece_data_index = self.bitboards_mut().at(index)?;
+        let piece_data = &mut self.pieces[piece_data_index];
+        piece_data.capture();
+        let (piece, color) = (piece_data.behavior.kind(), piece_data.color);
+        let unique_piece = piece_data.piece;
+        self.bitboards_mut().remove(piece, color, index);
+        self.toggle_piece_hash(piece, color, index);
+        Some(unique_piece)
+    }
+
+    // Puts a captured piece back on the board
+    pub(crate) fn restore_piece(
+        &mut self,
+        unique_piece: UniquePiece,
+        color: Color,
+        square: &Square,
+    ) -> Option<()> {
+        let index = square_index(square)?;
+        let piece_data_index = self.pieces.iter().position(|p| {
+            p.piece == unique_piece && p.color == color && p.curr_square().is_none()
+        })?;
+        let piece_data = &mut self.pieces[piece_data_index];
+        piece_data.move_unchecked(square.clone());
+        let piece = piece_data.behavior.kind();
+        self.bitboards_mut()
+            .add(piece_data_index, piece, color, index);
+        self.toggle_piece_hash(piece, color, index);
+        Some(())
+    }
+
+    // Changes the type of the piece on the square, for promotions and taking them back
+    pub(crate) fn set_piece_kind(&mut self, square: &Square, kind: PieceKind) -> Option<()> {
+        let index = square_index(square)?;
+        let piece_data_index = self.bitboards_mut().at(index)?;
+        let piece_data = &mut self.pieces[piece_data_index];
+        let (piece, color) = (piece_data.behavior.kind(), piece_data.color);
+        piece_data.behavior = rules::new_rules(kind);
+        let bitboards = self.bitboards_mut();
+        bitboards.remove(piece, color, index);
+        bitboards.add(piece_data_index, kind, color, index);
+        self.toggle_piece_hash(piece, color, index);
+        self.toggle_piece_hash(kind, color, index);
+        Some(())
+    }
+
+    // Bishop-like or rook-like moves of a piece, up to and including the first enemy piece
+    pub(crate) fn get_slider_squares(&self, piece_data: &PieceData, diagonal: bool) -> Vec<Square> {
+        let index = match piece_data.curr_square().and_then(square_index) {
+            Some(index) => index,
+            None => return Vec::new(),
+        };
+        let bitboards = self.bitboards();
+        let occupied = bitboards.occupied();
+        let attacks = if diagonal {
+            bishop_attacks(index, occupied)
+        } else {
+            rook_attacks(index, occupied)
+        };
+        to_squares(attacks & !bitboards.color(piece_data.color))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attack_tables() {
+        let a1 = square_index(&Square::A1).unwrap();
+        let d4 = square_index(&Square::D4).unwrap();
+        assert_eq!(knight_attacks(a1).count_ones(), 2);
+        assert_eq!(knight_attacks(d4).count_ones(), 8);
+        assert_eq!(king_attacks(a1).count_ones(), 3);
+        assert_eq!(
+            to_squares(pawn_attacks(Color::White, d4)),
+            vec![Square::C5, Square::E5]
+        );
+        assert_eq!(rook_attacks(d4, 0).count_ones(), 14);
+        assert_eq!(bishop_attacks(d4, 0).count_ones(), 13);
+
+        let blockers =
+            1 << square_index(&Square::D6).unwrap() | 1 << square_index(&Square::B2).unwrap();
+        let rook = to_squares(rook_attacks(d4, blockers));
+        assert!(rook.contains(&Square::D6) && !rook.contains(&Square::D7));
+        let bishop = to_squares(bishop_attacks(d4, blockers));
+        assert!(bishop.contains(&Square::B2) && !bishop.contains(&Square::A1));
+        assert!(bishop.contains(&Square::H8));
+    }
+}
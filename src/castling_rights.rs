@@ -0,0 +1,102 @@
+use chess_pgn_parser::{File, Rank, Square};
+
+use crate::Color;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> Self {
+        Self {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+        }
+    }
+
+    pub fn has(&self, king_side: bool, color: Color) -> bool {
+        match (king_side, color) {
+            (true, Color::White) => self.white_king_side,
+            (false, Color::White) => self.white_queen_side,
+            (true, Color::Black) => self.black_king_side,
+            (false, Color::Black) => self.black_queen_side,
+        }
+    }
+
+    pub fn grant(&mut self, king_side: bool, color: Color) {
+        match (king_side, color) {
+            (true, Color::White) => self.white_king_side = true,
+            (false, Color::White) => self.white_queen_side = true,
+            (true, Color::Black) => self.black_king_side = true,
+            (false, Color::Black) => self.black_queen_side = true,
+        }
+    }
+
+    pub fn revoke(&mut self, king_side: bool, color: Color) {
+        match (king_side, color) {
+            (true, Color::White) => self.white_king_side = false,
+            (false, Color::White) => self.white_queen_side = false,
+            (true, Color::Black) => self.black_king_side = false,
+            (false, Color::Black) => self.black_queen_side = false,
+        }
+    }
+
+    pub fn revoke_all(&mut self, color: Color) {
+        self.revoke(true, color);
+        self.revoke(false, color);
+    }
+
+    // A rook leaving or being captured on its initial square loses that wing for good
+    pub fn revoke_for_square(&mut self, square: &Square, files: &CastlingFiles) {
+        let color = match square.rank() {
+            Some(Rank::R1) => Color::White,
+            Some(Rank::R8) => Color::Black,
+            _ => return,
+        };
+        for king_side in [true, false] {
+            if square.file() == Some(files.rook(king_side)) {
+                self.revoke(king_side, color);
+            }
+        }
+    }
+}
+
+// Initial files of the king and of the rooks it castles with, the same for both colors. In
+// Chess960 they depend on the setup, the king always standing between the rooks.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct CastlingFiles {
+    pub king: File,
+    pub king_side_rook: File,
+    pub queen_side_rook: File,
+}
+
+impl CastlingFiles {
+    pub const STANDARD: CastlingFiles = CastlingFiles {
+        king: File::E,
+        king_side_rook: File::H,
+        queen_side_rook: File::A,
+    };
+
+    pub fn rook(&self, king_side: bool) -> File {
+        if king_side {
+            self.king_side_rook
+        } else {
+            self.queen_side_rook
+        }
+    }
+}
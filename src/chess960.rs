@@ -0,0 +1,227 @@
+use crate::{Board, ChessError, Color, PieceKind};
+
+// Number of Chess960 starting positions. Index 518 is the standard setup.
+pub const CHESS960_SETUPS: u16 = 960;
+
+// The white back rank of a Chess960 setup from a to h, numbered as Scharnagl does: the index
+// places the light squared bishop, the dark squared bishop, the queen and the knights, and
+// the remaining three squares take rook, king and rook.
+fn back_rank(index: u16) -> [PieceKind; 8] {
+    const KNIGHTS: [(usize, usize); 10] = [
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (1, 2),
+        (1, 3),
+        (1, 4),
+        (2, 3),
+        (2, 4),
+        (3, 4),
+    ];
+    let mut rank: [Option<PieceKind>; 8] = [None; 8];
+    let mut n = index as usize;
+    rank[2 * (n % 4) + 1] = Some(PieceKind::Bishop);
+    n /= 4;
+    rank[2 * (n % 4)] = Some(PieceKind::Bishop);
+    n /= 4;
+
+    // Puts the piece on the given empty square, counting from the a-file
+    let place = |rank: &mut [Option<PieceKind>; 8], empty_index: usize, piece: PieceKind| {
+        let file = (0..8).filter(|file| rank[*file].is_none()).nth(empty_index);
+        rank[file.unwrap()] = Some(piece);
+    };
+    place(&mut rank, n % 6, PieceKind::Queen);
+    n /= 6;
+    let (first, second) = KNIGHTS[n];
+    // The second knight goes first so the first one's empty square index stays put
+    place(&mut rank, second, PieceKind::Knight);
+    place(&mut rank, first, PieceKind::Knight);
+    for piece in [PieceKind::Rook, PieceKind::King, PieceKind::Rook] {
+        place(&mut rank, 0, piece);
+    }
+    rank.map(|piece| piece.unwrap())
+}
+
+impl Board {
+    // The Chess960 starting position with the given index, from 0 to 959. Castling on the
+    // board is written as the king taking its own rook.
+    pub fn from_chess960(index: u16) -> Result<Board, ChessError> {
+        if index >= CHESS960_SETUPS {
+            return Err(ChessError::InvalidChess960Index(index));
+        }
+        let black: String = back_rank(index)
+            .iter()
+            .map(|piece| Self::piece_to_char(*piece, Color::Black))
+            .collect();
+        let fen = format!(
+            "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+            black,
+            black.to_ascii_uppercase()
+        );
+        let mut board = Board::from_fen(&fen)?;
+        board.chess960 = true;
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::square_index;
+    use crate::{Board, CastlingFiles, ChessError, File, PieceKind, Square, STARTING_FEN};
+
+    #[test]
+    fn test_chess960_setups() -> Result<(), ChessError> {
+        let board = Board::from_chess960(518)?;
+        assert_eq!(board.to_fen(), STARTING_FEN);
+        assert!(board.is_chess960());
+        assert_eq!(board.castling_files(), CastlingFiles::STANDARD);
+
+        let board = Board::from_chess960(0)?;
+        assert_eq!(
+            board.to_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert_eq!(board.to_shredder_fen().split(' ').nth(2), Some("HFhf"));
+        assert_eq!(
+            Board::from_chess960(959)?.to_fen(),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1"
+        );
+        assert!(matches!(
+            Board::from_chess960(960),
+            Err(ChessError::InvalidChess960Index(960))
+        ));
+
+        // Every setup has the king between the rooks and bishops on both square colors
+        for index in 0..960 {
+            let board = Board::from_chess960(index)?;
+            let files = board.castling_files();
+            let file = |file| square_index::file_index(file);
+            assert!(file(files.queen_side_rook) < file(files.king));
+            assert!(file(files.king) < file(files.king_side_rook));
+            let bishop_files: Vec<i32> = (0..8)
+                .filter(|file| {
+                    board
+                        .get_piece_data_at_square(&square_index::square_at(*file, 0).unwrap())
+                        .is_some_and(|p| p.behavior.kind() == PieceKind::Bishop)
+                })
+                .collect();
+            assert_eq!(bishop_files.len(), 2);
+            assert_ne!(bishop_files[0] % 2, bishop_files[1] % 2);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_chess960_castling() -> Result<(), ChessError> {
+        // King and rook trade squares when castling king side
+        let mut board = Board::from_fen("1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w GBgb - 0 1")?;
+        assert!(board.is_chess960());
+        assert!(board
+            .legal_moves_from_square(&Square::F1)
+            .contains(&Square::G1));
+        assert_eq!(board.to_san(&Square::F1, &Square::G1, None)?, "O-O");
+        let fen = board.to_fen();
+        board.simple_move(&Square::F1, &Square::G1, None)?;
+        assert_eq!(
+            board.to_fen(),
+            "1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 b kq - 1 1"
+        );
+        board.undo();
+        assert_eq!(board.to_fen(), fen);
+
+        board.simple_move(&Square::F1, &Square::B1, None)?;
+        board.simple_move(&Square::F8, &Square::B8, None)?;
+        assert_eq!(
+            board.to_fen(),
+            "2kr2r1/pppppppp/8/8/8/8/PPPPPPPP/2KR2R1 w - - 2 2"
+        );
+        assert_eq!(board.san_moves()?, vec!["O-O-O", "O-O-O"]);
+        assert_eq!(board.move_list()[0].to, Square::B1);
+
+        // The king may stay put, only the rook moving
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R1K5 w A - 0 1")?;
+        board.add_pgn_moves("1. O-O-O")?;
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_chess960_notation_required() -> Result<(), ChessError> {
+        // Castling queen side takes the king to c1, which it can also just step to
+        let mut board = Board::from_fen("rk5r/pppppppp/8/8/8/8/PPPPPPPP/RK5R w HAha - 0 1")?;
+        assert!(matches!(
+            board.set_chess960(false),
+            Err(ChessError::Chess960CastlingRequired)
+        ));
+        assert!(board.is_chess960());
+        let moves = board.legal_moves_uci();
+        assert_eq!(moves.iter().filter(|m| *m == "b1c1").count(), 1);
+        assert!(moves.contains(&"b1a1".to_string()));
+
+        let fen = board.to_fen();
+        board.apply_uci("b1a1")?;
+        assert_eq!(
+            board.to_fen(),
+            "rk5r/pppppppp/8/8/8/8/PPPPPPPP/2KR3R b kq - 1 1"
+        );
+        board.undo();
+        assert_eq!(board.to_fen(), fen);
+
+        let mut board = Board::new();
+        board.set_chess960(true)?;
+        board.set_chess960(false)?;
+        assert!(!board.is_chess960());
+        Ok(())
+    }
+
+    #[test]
+    fn test_chess960_castling_rules() -> Result<(), ChessError> {
+        // The squares between the rook and its destination must be empty
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/RN2K3 w A - 0 1")?;
+        assert!(!board
+            .legal_moves_from_square(&Square::E1)
+            .contains(&Square::A1));
+
+        // The castling rook can't shield the king's destination from an attack behind it
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/r1RK4 w C - 0 1")?;
+        assert!(!board
+            .legal_moves_from_square(&Square::D1)
+            .contains(&Square::C1));
+
+        // The rook may be attacked
+        let board = Board::from_fen("1r2k3/8/8/8/8/8/8/1R4K1 w B - 0 1")?;
+        assert!(board
+            .legal_moves_from_square(&Square::G1)
+            .contains(&Square::B1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_chess960_fen() -> Result<(), ChessError> {
+        // X-FEN names an inner rook by its file
+        let fen = "rk2r3/8/8/8/8/8/8/RK2R2R w EAa - 0 1";
+        let board = Board::from_fen(fen)?;
+        assert_eq!(board.to_fen(), "rk2r3/8/8/8/8/8/8/RK2R2R w EQq - 0 1");
+        assert_eq!(board.to_shredder_fen(), fen);
+        assert_eq!(board.castling_files().king_side_rook, File::E);
+
+        let board = Board::from_fen(STARTING_FEN.replace("KQkq", "HAha").as_str())?;
+        assert!(board.is_chess960());
+        assert_eq!(board.to_fen(), STARTING_FEN);
+        assert!(!Board::new().is_chess960());
+
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            "4k3/8/8/8/8/8/8/4K2R w B - 0 1",
+            "r3k3/8/8/8/8/8/8/1R2K3 w Qq - 0 1",
+        ] {
+            assert!(
+                matches!(Board::from_fen(fen), Err(ChessError::FenParseError(_))),
+                "{}",
+                fen
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt;
+
+use chess_pgn_parser::Square;
+
+use crate::bitboard;
+use crate::piece_data::PieceData;
+use crate::square_index;
+use crate::{Board, ChessError, PieceKind};
+
+const CAPTURE: u8 = 1;
+const EN_PASSANT: u8 = 1 << 1;
+const CASTLING: u8 = 1 << 2;
+const DOUBLE_PUSH: u8 = 1 << 3;
+
+// A legal move of the side to move, as generated by `Board::legal_moves`. Squares are kept
+// as indices, a1 = 0 to h8 = 63, and what else the move does as flags.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Move {
+    from: u8,
+    to: u8,
+    promotion: Option<PieceKind>,
+    flags: u8,
+}
+
+impl Move {
+    fn new(from: &Square, to: &Square, promotion: Option<PieceKind>, flags: u8) -> Move {
+        let index =
+            |square| bitboard::square_index(square).expect("moves are between known squares");
+        Move {
+            from: index(from) as u8,
+            to: index(to) as u8,
+            promotion,
+            flags,
+        }
+    }
+
+    pub fn from(&self) -> Square {
+        bitboard::index_square(self.from as usize)
+    }
+
+    // For castling the king's destination, or on Chess960 boards the square of the rook it
+    // castles with
+    pub fn to(&self) -> Square {
+        bitboard::index_square(self.to as usize)
+    }
+
+    pub fn promotion(&self) -> Option<PieceKind> {
+        self.promotion
+    }
+
+    pub fn is_promotion(&self) -> bool {
+        self.promotion.is_some()
+    }
+
+    // En passant included
+    pub fn is_capture(&self) -> bool {
+        self.flags & CAPTURE != 0
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.flags & EN_PASSANT != 0
+    }
+
+    pub fn is_castling(&self) -> bool {
+        self.flags & CASTLING != 0
+    }
+
+    // A pawn advancing two squares from its initial rank
+    pub fn is_double_push(&self) -> bool {
+        self.flags & DOUBLE_PUSH != 0
+    }
+}
+
+// The squares as UCI writes them, like "e2e4" or "e7e8q"
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Board::get_uci(&self.from(), &self.to(), self.promotion) {
+            Some(uci) => write!(f, "{}", uci),
+            None => Err(fmt::Error),
+        }
+    }
+}
+
+impl Board {
+    // Every legal move of the side to move. A pawn reaching the last rank gives one move per
+    // piece it can promote to.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for piece_data in self.pieces.iter() {
+            let from = match piece_data.curr_square() {
+                Some(from) if piece_data.color == self.side_to_move => from,
+                _ => continue,
+            };
+            let is_pawn = piece_data.behavior.kind() == PieceKind::Pawn;
+            for to in piece_data.behavior.get_valid_squares(piece_data, self) {
+                let flags = self.get_move_flags(piece_data, from, &to);
+                let (_, to_rank) = square_index::square_coords(&to).expect("known square");
+                // Pawns never move backwards, so either end of the board is their last rank
+                if is_pawn && (to_rank == 0 || to_rank == 7) {
+                    for promotion in PieceKind::PROMOTIONS.iter() {
+                        moves.push(Move::new(from, &to, Some(*promotion), flags));
+                    }
+                } else {
+                    moves.push(Move::new(from, &to, None, flags));
+                }
+            }
+        }
+        moves
+    }
+
+    // Plays a move, like one from `legal_moves`
+    pub fn apply_move(&mut self, m: &Move) -> Result<(), ChessError> {
+        self.simple_move(&m.from(), &m.to(), m.promotion)
+    }
+
+    fn get_move_flags(&self, piece_data: &PieceData, from: &Square, to: &Square) -> u8 {
+        if self.get_castling_side(piece_data, to).is_some() {
+            return CASTLING;
+        }
+        let is_pawn = piece_data.behavior.kind() == PieceKind::Pawn;
+        let (from_file, from_rank) = square_index::square_coords(from).expect("known square");
+        let (to_file, to_rank) = square_index::square_coords(to).expect("known square");
+        match self.get_piece_data_at_square(to) {
+            Some(_) => CAPTURE,
+            // A pawn only moves diagonally onto an empty square to take en passant
+            None if is_pawn && from_file != to_file => CAPTURE | EN_PASSANT,
+            None if is_pawn && (to_rank - from_rank).abs() == 2 => DOUBLE_PUSH,
+            None => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, ChessError, Move, PieceKind, Square};
+
+    fn find(moves: &[Move], from: Square, to: Square) -> Vec<Move> {
+        moves
+            .iter()
+            .filter(|m| m.from() == from && m.to() == to)
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_legal_moves() -> Result<(), ChessError> {
+        let moves = Board::new().legal_moves();
+        assert_eq!(moves.len(), 20);
+        let knight = find(&moves, Square::G1, Square::F3)[0];
+        assert!(!knight.is_capture() && !knight.is_double_push() && !knight.is_promotion());
+        let pawn = find(&moves, Square::E2, Square::E4)[0];
+        assert!(pawn.is_double_push());
+        assert_eq!(pawn.to_string(), "e2e4");
+        assert_eq!(std::mem::size_of::<Move>(), 4);
+
+        // Each promotion piece is a move of its own, on a push or a capture
+        let moves = Board::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1")?.legal_moves();
+        let push = find(&moves, Square::B7, Square::B8);
+        let capture = find(&moves, Square::B7, Square::A8);
+        assert_eq!(push.len(), 4);
+        assert_eq!(capture.len(), 4);
+        for piece in PieceKind::PROMOTIONS.iter() {
+            assert!(push
+                .iter()
+                .any(|m| m.promotion() == Some(*piece) && !m.is_capture()));
+            assert!(capture
+                .iter()
+                .any(|m| m.promotion() == Some(*piece) && m.is_capture()));
+        }
+        assert_eq!(capture[0].to_string(), "b7a8q");
+
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1")?;
+        let en_passant = find(&board.legal_moves(), Square::E5, Square::D6)[0];
+        assert!(en_passant.is_en_passant() && en_passant.is_capture());
+        Ok(())
+    }
+
+    #[test]
+    fn test_legal_moves_castling() -> Result<(), ChessError> {
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+        let castling: Vec<Move> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|m| m.is_castling())
+            .collect();
+        assert_eq!(castling.len(), 2);
+        assert!(castling.iter().any(|m| m.to() == Square::G1));
+        assert!(castling.iter().any(|m| m.to() == Square::C1));
+        assert!(castling.iter().all(|m| !m.is_capture()));
+
+        board.apply_move(&castling[0])?;
+        assert!(board.move_list().last().unwrap().castling);
+
+        // On Chess960 boards the king moves onto its rook
+        let board = Board::from_fen("1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w GBgb - 0 1")?;
+        let castling: Vec<Square> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|m| m.is_castling())
+            .map(|m| m.to())
+            .collect();
+        assert_eq!(castling.len(), 2);
+        assert!(castling.contains(&Square::G1));
+        assert!(castling.contains(&Square::B1));
+
+        // Every move generated can be played
+        let board = Board::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        )?;
+        let moves = board.legal_moves();
+        assert_eq!(moves.len(), 48);
+        assert_eq!(moves.iter().filter(|m| m.is_capture()).count(), 8);
+        for m in moves.iter() {
+            board.clone().apply_move(m)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::ops::Not;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    pub const BOTH: [Color; 2] = [Color::White, Color::Black];
+
+    pub fn is_white(self) -> bool {
+        self == Color::White
+    }
+
+    pub fn from_white(white: bool) -> Color {
+        if white {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+
+    // Position in `BOTH`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+impl Not for Color {
+    type Output = Color;
+
+    fn not(self) -> Color {
+        self.opposite()
+    }
+}
@@ -0,0 +1,181 @@
+use crate::bitboard;
+use crate::{Board, Color, PieceKind};
+
+// Centipawn values in the order of `PieceKind::ALL`
+pub(crate) const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+// Game phase weight of each piece in the order of `PieceKind::ALL`. The phase runs from
+// OPENING_PHASE with all pieces on the board down to 0 with only kings and pawns.
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+const OPENING_PHASE: i32 = 24;
+
+// Piece-square bonuses for white, written as the board is seen from white's side: the first
+// row is rank 8. Black reads them mirrored.
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+// The king hides behind its pawns while the opponent has pieces to attack with
+#[rustfmt::skip]
+const KING_OPENING_TABLE: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+// and heads for the centre once they're gone
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+const TABLES: [&[i32; 64]; 5] = [
+    &PAWN_TABLE,
+    &KNIGHT_TABLE,
+    &BISHOP_TABLE,
+    &ROOK_TABLE,
+    &QUEEN_TABLE,
+];
+
+// Index into the tables of a square index (a1 = 0) for the given colour
+fn table_index(index: usize, color: Color) -> usize {
+    if color.is_white() {
+        index ^ 56
+    } else {
+        index
+    }
+}
+
+impl Board {
+    // Static evaluation in centipawns from the point of view of the side to move: material
+    // plus piece-square bonuses, with the king's table blended from opening to endgame
+    pub fn evaluate(&self) -> i32 {
+        let bitboards = self.bitboards();
+        let mut phase = 0;
+        let mut score = 0;
+        for color in Color::BOTH {
+            let sign = if color.is_white() { 1 } else { -1 };
+            for piece in PieceKind::ALL.iter() {
+                let pieces = bitboards.pieces(*piece, color);
+                phase += PHASE_WEIGHTS[piece.index()] * pieces.count_ones() as i32;
+                score += sign * PIECE_VALUES[piece.index()] * pieces.count_ones() as i32;
+                if let Some(table) = TABLES.get(piece.index()) {
+                    for index in bitboard::to_indices(pieces) {
+                        score += sign * table[table_index(index, color)];
+                    }
+                }
+            }
+        }
+        let phase = phase.min(OPENING_PHASE);
+        for color in Color::BOTH {
+            let sign = if color.is_white() { 1 } else { -1 };
+            for index in bitboard::to_indices(bitboards.pieces(PieceKind::King, color)) {
+                let index = table_index(index, color);
+                score += sign
+                    * (KING_OPENING_TABLE[index] * phase
+                        + KING_ENDGAME_TABLE[index] * (OPENING_PHASE - phase))
+                    / OPENING_PHASE;
+            }
+        }
+        if self.side_to_move.is_white() {
+            score
+        } else {
+            -score
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, ChessError};
+
+    #[test]
+    fn test_evaluate() -> Result<(), ChessError> {
+        assert_eq!(Board::new().evaluate(), 0);
+
+        // Mirrored positions score the same for the side to move
+        let white = Board::from_fen("4k3/8/8/8/3N4/8/1PP5/4K3 w - - 0 1")?;
+        let black = Board::from_fen("4k3/1pp5/8/3n4/8/8/8/4K3 b - - 0 1")?;
+        assert_eq!(white.evaluate(), black.evaluate());
+        assert!(white.evaluate() > 500);
+
+        // A centralised knight beats one on the rim
+        let centre = Board::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1")?;
+        let rim = Board::from_fen("4k3/8/8/8/N7/8/8/4K3 w - - 0 1")?;
+        assert!(centre.evaluate() > rim.evaluate());
+
+        // In the endgame the king is better off in the centre
+        let centre = Board::from_fen("4k3/8/8/8/3K4/8/8/8 w - - 0 1")?;
+        let corner = Board::from_fen("4k3/8/8/8/8/8/8/K7 w - - 0 1")?;
+        assert!(centre.evaluate() > corner.evaluate());
+        Ok(())
+    }
+}
@@ -0,0 +1,468 @@
+use std::cell::{Cell, RefCell};
+
+use chess_pgn_parser::{File, Rank, Square};
+
+use crate::bitboard::Bitboards;
+use crate::piece_data::PieceData;
+use crate::rules::{self, UniquePiece};
+use crate::{square_index, zobrist, Color, PieceKind};
+use crate::{Board, CastlingFiles, CastlingRights, ChessError};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+fn fen_error(reason: &str) -> ChessError {
+    ChessError::FenParseError(reason.to_string())
+}
+
+impl Board {
+    // Accepts the six FEN fields, or just the first four with the clocks defaulting to "0 1".
+    // Castling rights may also be given as in Shredder-FEN or X-FEN, and a board whose rooks
+    // don't castle from the standard squares plays as Chess960.
+    pub fn from_fen(fen: &str) -> Result<Board, ChessError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 && fields.len() != 4 {
+            return Err(fen_error("expected 4 or 6 space separated fields"));
+        }
+
+        let placement = Self::parse_fen_placement(fields[0])?;
+        let mut pieces = Self::assign_identities(&placement, Color::White)?;
+        pieces.extend(Self::assign_identities(&placement, Color::Black)?);
+
+        let side_to_move = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(fen_error("side to move must be 'w' or 'b'")),
+        };
+        let (castling_rights, castling_files) = Self::parse_fen_castling(fields[2], &placement)?;
+        let chess960 = castling_files != CastlingFiles::STANDARD
+            || fields[2].chars().any(|c| !"KQkq-".contains(c));
+        let en_passant_square = match fields[3] {
+            "-" => None,
+            name => Some(
+                square_index::parse_square(name)
+                    .ok_or_else(|| fen_error("invalid en passant square"))?,
+            ),
+        };
+        let (halfmove_clock, fullmove_number) = if fields.len() == 6 {
+            (
+                fields[4]
+                    .parse()
+                    .map_err(|_| fen_error("invalid halfmove clock"))?,
+                fields[5]
+                    .parse()
+                    .map_err(|_| fen_error("invalid fullmove number"))?,
+            )
+        } else {
+            (0, 1)
+        };
+        if fullmove_number == 0 {
+            return Err(fen_error("fullmove number starts at 1"));
+        }
+
+        let bitboards = Bitboards::from_pieces(&pieces);
+        let placement_hash = zobrist::placement_hash(&bitboards);
+        let mut board = Board {
+            pieces,
+            castling_rights,
+            castling_files,
+            chess960,
+            en_passant_square,
+            side_to_move,
+            halfmove_clock,
+            fullmove_number,
+            position_keys: Vec::new(),
+            starting_fen: String::new(),
+            move_list: Vec::new(),
+            redo_list: Vec::new(),
+            bitboards: RefCell::new(bitboards),
+            stale_bitboards: Cell::new(false),
+            hash: Cell::new(placement_hash),
+        };
+        board.hash.set(placement_hash ^ board.state_hash());
+        if board.is_in_check(!side_to_move) {
+            return Err(fen_error("the side not to move is in check"));
+        }
+        board.starting_fen = board.to_fen();
+        board.record_position();
+        Ok(board)
+    }
+
+    // Chess960 boards write castling rights as in X-FEN
+    pub fn to_fen(&self) -> String {
+        self.get_fen(false)
+    }
+
+    // Like `to_fen`, but with castling rights given by the files of the rooks
+    pub fn to_shredder_fen(&self) -> String {
+        self.get_fen(true)
+    }
+
+    fn get_fen(&self, shredder: bool) -> String {
+        let en_passant = self
+            .en_passant_square
+            .as_ref()
+            .and_then(square_index::square_name)
+            .unwrap_or_else(|| "-".to_string());
+        format!(
+            "{} {} {} {} {} {}",
+            self.get_fen_placement(),
+            if self.side_to_move.is_white() {
+                'w'
+            } else {
+                'b'
+            },
+            self.get_fen_castling(shredder),
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    pub(crate) fn get_fen_placement(&self) -> String {
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty_squares = 0;
+            for file in 0..8 {
+                let square = square_index::square_at(file, rank).unwrap();
+                match self.get_piece_data_at_square(&square) {
+                    Some(p) => {
+                        if empty_squares > 0 {
+                            placement.push_str(&empty_squares.to_string());
+                            empty_squares = 0;
+                        }
+                        placement.push(Self::piece_to_char(p.behavior.kind(), p.color));
+                    }
+                    None => empty_squares += 1,
+                }
+            }
+            if empty_squares > 0 {
+                placement.push_str(&empty_squares.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+        placement
+    }
+
+    // X-FEN writes 'K' and 'Q' unless another rook stands further out on that wing, in which
+    // case the rook's file is written instead, as Shredder-FEN always does
+    fn get_fen_castling(&self, shredder: bool) -> String {
+        let mut castling = String::new();
+        for color in Color::BOTH {
+            for king_side in [true, false] {
+                if !self.castling_rights.has(king_side, color) {
+                    continue;
+                }
+                let rook_file = self.castling_files.rook(king_side);
+                let c = if shredder
+                    || self.get_outermost_rook_file(king_side, color) != Some(rook_file)
+                {
+                    (b'a' + square_index::file_index(rook_file) as u8) as char
+                } else if king_side {
+                    'k'
+                } else {
+                    'q'
+                };
+                castling.push(if color.is_white() {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                });
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        castling
+    }
+
+    fn get_outermost_rook_file(&self, king_side: bool, color: Color) -> Option<File> {
+        let rank = if color.is_white() { Rank::R1 } else { Rank::R8 };
+        let rooks = self
+            .get_all_live_piece_data_with_type(PieceKind::Rook, color)
+            .into_iter()
+            .filter_map(|p| p.curr_square())
+            .filter(|square| square.rank() == Some(rank))
+            .filter_map(|square| square.file());
+        if king_side {
+            rooks.max_by_key(|file| square_index::file_index(*file))
+        } else {
+            rooks.min_by_key(|file| square_index::file_index(*file))
+        }
+    }
+
+    // Pieces as (kind, color, square), ordered from a1 to h8
+    fn parse_fen_placement(placement: &str) -> Result<Vec<(PieceKind, Color, Square)>, ChessError> {
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(fen_error("placement must have 8 ranks"));
+        }
+
+        let mut pieces = Vec::new();
+        for (rank, rank_str) in (0..8).rev().zip(ranks.iter()) {
+            let mut file = 0;
+            for c in rank_str.chars() {
+                if let Some(empty_squares) = c.to_digit(10) {
+                    file += empty_squares as i32;
+                    continue;
+                }
+                let (piece, color) =
+                    Self::char_to_piece(c).ok_or_else(|| fen_error("invalid piece letter"))?;
+                let square = square_index::square_at(file, rank)
+                    .ok_or_else(|| fen_error("rank has more than 8 squares"))?;
+                if piece == PieceKind::Pawn && (rank == 0 || rank == 7) {
+                    return Err(fen_error("pawns can't stand on the first or last rank"));
+                }
+                pieces.push((piece, color, square));
+                file += 1;
+            }
+            if file != 8 {
+                return Err(fen_error("every rank must describe 8 squares"));
+            }
+        }
+        pieces.sort_by_key(|(_, _, square)| {
+            let (file, rank) = square_index::square_coords(square).unwrap();
+            rank * 8 + file
+        });
+        Ok(pieces)
+    }
+
+    // Reads castling rights as standard FEN, Shredder-FEN or X-FEN. 'K' and 'Q' stand for the
+    // outermost rook on that side of the king and a file letter for the rook on that file.
+    // Both colors must castle with rooks on the same files, as they do in every Chess960 setup.
+    fn parse_fen_castling(
+        castling: &str,
+        placement: &[(PieceKind, Color, Square)],
+    ) -> Result<(CastlingRights, CastlingFiles), ChessError> {
+        let mut castling_rights = CastlingRights::none();
+        let mut files = CastlingFiles::STANDARD;
+        let (mut king_file, mut rook_files) = (None, [None, None]);
+        if castling == "-" {
+            return Ok((castling_rights, files));
+        }
+        for c in castling.chars() {
+            let color = Color::from_white(c.is_ascii_uppercase());
+            let back_rank = if color.is_white() { 0 } else { 7 };
+            let back_rank_files = |kind| {
+                placement
+                    .iter()
+                    .filter(move |(piece, w, _)| *piece == kind && *w == color)
+                    .filter_map(|(_, _, square)| square_index::square_coords(square))
+                    .filter(|(_, rank)| *rank == back_rank)
+                    .map(|(file, _)| file)
+            };
+            let king = back_rank_files(PieceKind::King)
+                .next()
+                .ok_or_else(|| fen_error("castling rights without a king on the back rank"))?;
+            let mut rooks = back_rank_files(PieceKind::Rook);
+            let rook = match c.to_ascii_lowercase() {
+                'k' => rooks.filter(|file| *file > king).max(),
+                'q' => rooks.filter(|file| *file < king).min(),
+                'a'..='h' => {
+                    let file = c.to_ascii_lowercase() as i32 - 'a' as i32;
+                    rooks.find(|rook| *rook == file)
+                }
+                _ => return Err(fen_error("invalid castling rights")),
+            }
+            .ok_or_else(|| fen_error("castling rights without a rook to castle with"))?;
+            let king_side = rook > king;
+            let wing = king_side as usize;
+            if king_file.is_some_and(|file| file != king)
+                || rook_files[wing].is_some_and(|file| file != rook)
+            {
+                return Err(fen_error("both sides must castle from the same files"));
+            }
+            king_file = Some(king);
+            rook_files[wing] = Some(rook);
+            files.king = square_index::file_at(king).unwrap();
+            if king_side {
+                files.king_side_rook = square_index::file_at(rook).unwrap();
+            } else {
+                files.queen_side_rook = square_index::file_at(rook).unwrap();
+            }
+            castling_rights.grant(king_side, color);
+        }
+        Ok((castling_rights, files))
+    }
+
+    // Gives every piece of one color a UniquePiece. Pieces on their initial square (pawns on
+    // their initial file) keep that identity, the rest take a free identity of their type in
+    // file order, and any extra piece (only reachable through promotion) takes the identity of
+    // a missing pawn.
+    fn assign_identities(
+        placement: &[(PieceKind, Color, Square)],
+        color: Color,
+    ) -> Result<Vec<PieceData>, ChessError> {
+        let placement: Vec<&(PieceKind, Color, Square)> =
+            placement.iter().filter(|(_, w, _)| *w == color).collect();
+        if placement
+            .iter()
+            .filter(|(piece, _, _)| *piece == PieceKind::King)
+            .count()
+            != 1
+        {
+            return Err(fen_error("each side must have exactly one king"));
+        }
+
+        let back_rank = if color.is_white() { Rank::R1 } else { Rank::R8 };
+        let mut free_identities = UniquePiece::ALL.to_vec();
+        let mut identities: Vec<Option<UniquePiece>> = vec![None; placement.len()];
+        for pass in 0..3 {
+            for (identity, (piece, _, square)) in identities.iter_mut().zip(placement.iter()) {
+                if identity.is_some() {
+                    continue;
+                }
+                if let Some(index) = free_identities.iter().position(|unique| {
+                    Self::identity_fits(pass, *unique, *piece, square, back_rank)
+                }) {
+                    *identity = Some(free_identities.remove(index));
+                }
+            }
+        }
+
+        identities
+            .into_iter()
+            .zip(placement)
+            .map(|(identity, (piece, _, square))| {
+                let identity = identity.ok_or_else(|| fen_error("too many pieces for one side"))?;
+                Ok(PieceData::new(
+                    identity,
+                    color,
+                    rules::new_rules(*piece),
+                    square.clone(),
+                ))
+            })
+            .collect()
+    }
+
+    // Pass 0 matches pieces on the identity's initial square, pass 1 any piece of the same
+    // type and pass 2 any piece to a pawn identity, as if it had promoted
+    fn identity_fits(
+        pass: usize,
+        unique: UniquePiece,
+        piece: PieceKind,
+        square: &Square,
+        back_rank: Rank,
+    ) -> bool {
+        match pass {
+            0 => {
+                unique.initial_piece() == piece
+                    && match piece {
+                        PieceKind::Pawn => square.file() == Some(unique.initial_file()),
+                        _ => *square == Square::new_known(unique.initial_file(), back_rank),
+                    }
+            }
+            1 => unique.initial_piece() == piece,
+            _ => unique.initial_piece() == PieceKind::Pawn,
+        }
+    }
+
+    pub(crate) fn char_to_piece(c: char) -> Option<(PieceKind, Color)> {
+        let piece = match c.to_ascii_lowercase() {
+            'p' => PieceKind::Pawn,
+            'n' => PieceKind::Knight,
+            'b' => PieceKind::Bishop,
+            'r' => PieceKind::Rook,
+            'q' => PieceKind::Queen,
+            'k' => PieceKind::King,
+            _ => return None,
+        };
+        Some((piece, Color::from_white(c.is_ascii_uppercase())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, ChessError, Color, PieceKind, UniquePiece, STARTING_FEN};
+    use chess_pgn_parser::Square;
+
+    #[test]
+    fn test_starting_position_round_trip() -> Result<(), ChessError> {
+        let board = Board::from_fen(STARTING_FEN)?;
+        assert_eq!(board.to_fen(), STARTING_FEN);
+        assert_eq!(Board::new().to_fen(), STARTING_FEN);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fen_after_moves() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 c5 2. Nf3")?;
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fen_fields() -> Result<(), ChessError> {
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w Kq f6 0 3";
+        let board = Board::from_fen(fen)?;
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(board.side_to_move(), Color::White);
+        assert!(board.castling_rights().white_king_side);
+        assert!(!board.castling_rights().white_queen_side);
+        assert_eq!(board.en_passant_square(), Some(&Square::F6));
+        assert_eq!(board.fullmove_number(), 3);
+        assert!(board
+            .legal_moves_from_square(&Square::E5)
+            .contains(&Square::F6));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fen_identities() -> Result<(), ChessError> {
+        // Two white queens: the extra one must be a promoted pawn
+        let board = Board::from_fen("4k3/8/8/8/8/8/1PPPPPPP/Q2QK3 w - - 0 1")?;
+        let queen = board
+            .get_piece_data_at_square(&Square::D1)
+            .expect("missing piece.");
+        assert_eq!(queen.piece, UniquePiece::Queen);
+        let promoted_queen = board
+            .get_piece_data_at_square(&Square::A1)
+            .expect("missing piece.");
+        assert_eq!(promoted_queen.piece, UniquePiece::APawn);
+        assert_eq!(promoted_queen.behavior.kind(), PieceKind::Queen);
+
+        // Pawns away from their initial file take a free pawn identity
+        let board = Board::from_fen("4k3/8/8/8/3P4/8/8/4K3 w - - 0 1")?;
+        let pawn = board
+            .get_piece_data_at_square(&Square::D4)
+            .expect("missing piece.");
+        assert_eq!(pawn.piece, UniquePiece::DPawn);
+        assert_eq!(board.legal_moves_from_square(&Square::D4), vec![Square::D5]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_fen() {
+        for fen in [
+            "",
+            "8/8/8/8/8/8/8/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+            "4k3/8/8/8/8/8/8/4K2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w X - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - e9 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 0",
+            "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/4Q3/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/PPPPPPPP/NNNNK3 w - - 0 1",
+        ] {
+            assert!(
+                matches!(Board::from_fen(fen), Err(ChessError::FenParseError(_))),
+                "{}",
+                fen
+            );
+        }
+
+        // Only one of the clocks
+        match Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0") {
+            Err(ChessError::FenParseError(reason)) => {
+                assert_eq!(reason, "expected 4 or 6 space separated fields")
+            }
+            _ => panic!("five fields accepted"),
+        }
+    }
+}
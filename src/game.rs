@@ -0,0 +1,315 @@
+use crate::{Board, ChessError, Color, GameNode, GameTree, Outcome, STARTING_FEN};
+
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+const MAX_LINE_LENGTH: usize = 80;
+
+// A game tree together with the PGN tag pairs describing the game played on it. The main line
+// of the tree is the game itself, other lines are variations.
+#[derive(Clone)]
+pub struct Game {
+    tags: Vec<(String, String)>,
+    tree: GameTree,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Self::from_tree(GameTree::new())
+    }
+
+    // A game whose main line is the moves played on `board`
+    pub fn from_board(board: Board) -> Result<Game, ChessError> {
+        let mut tree = GameTree::from_board(board.get_starting_board()?);
+        let mut path = Vec::new();
+        for record in board.move_list() {
+            path = tree.add_record(&path, record.clone());
+        }
+        Ok(Self::from_tree(tree))
+    }
+
+    pub(crate) fn from_tree(tree: GameTree) -> Game {
+        Game {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            tree,
+        }
+    }
+
+    // The board at the end of the main line
+    pub fn board(&self) -> Board {
+        self.tree
+            .board_at(&self.tree.mainline(&[]))
+            .expect("moves in the tree are legal")
+    }
+
+    pub fn tree(&self) -> &GameTree {
+        &self.tree
+    }
+
+    pub fn tree_mut(&mut self) -> &mut GameTree {
+        &mut self.tree
+    }
+
+    // Plays PGN moves at the end of the main line
+    pub fn add_pgn_moves(&mut self, pgn_moves: &str) -> Result<(), ChessError> {
+        let mut path = self.tree.mainline(&[]);
+        let mut board = self.tree.board_at(&path)?;
+        let played = board.move_list().len();
+        board.add_pgn_moves(pgn_moves)?;
+        for record in board.move_list()[played..].iter() {
+            path = self.tree.add_record(&path, record.clone());
+        }
+        Ok(())
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some((_, tag_value)) => *tag_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // The result token. A finished game on the board decides it, otherwise the Result tag does.
+    pub fn result(&self) -> &str {
+        match self.board().outcome() {
+            Some(Outcome::Checkmate {
+                winner: Color::White,
+            }) => "1-0",
+            Some(Outcome::Checkmate {
+                winner: Color::Black,
+            }) => "0-1",
+            Some(Outcome::ThreefoldRepetition) | Some(Outcome::FiftyMoveRule) | None => {
+                self.tag("Result").unwrap_or("*")
+            }
+            Some(_) => "1/2-1/2",
+        }
+    }
+
+    pub fn to_pgn(&self) -> Result<String, ChessError> {
+        let result = self.result().to_string();
+        let mut tags: Vec<(&str, &str)> = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|(name, default)| (*name, self.tag(name).unwrap_or(default)))
+            .collect();
+        tags[6].1 = &result;
+        let starting_board = self.tree.starting_board();
+        let starting_fen = starting_board.starting_fen();
+        if starting_board.is_chess960() {
+            tags.push(("Variant", self.tag("Variant").unwrap_or("Chess960")));
+        }
+        if starting_fen != STARTING_FEN || starting_board.is_chess960() {
+            tags.push(("SetUp", "1"));
+            tags.push(("FEN", starting_fen));
+        }
+        for (name, value) in self.tags.iter() {
+            if !tags.iter().any(|(tag_name, _)| tag_name == name) {
+                tags.push((name, value));
+            }
+        }
+
+        let mut pgn = String::new();
+        for (name, value) in tags {
+            pgn.push_str(&format!(
+                "[{} \"{}\"]\n",
+                name,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+        }
+        pgn.push('\n');
+
+        let mut tokens = self.get_movetext_tokens()?;
+        tokens.push(result);
+        pgn.push_str(&Self::wrap_tokens(&tokens));
+        pgn.push('\n');
+        Ok(pgn)
+    }
+
+    // Movetext tokens of the main line, with variations in parentheses after the move they
+    // replace. Move numbers go in front of white's moves, and in front of black's moves at the
+    // start of a line or after a comment or variation.
+    fn get_movetext_tokens(&self) -> Result<Vec<String>, ChessError> {
+        let mut tokens: Vec<String> = self
+            .tree
+            .comments
+            .iter()
+            .map(|comment| format!("{{{}}}", comment))
+            .collect();
+        Self::push_line_tokens(
+            &mut tokens,
+            self.tree.starting_board(),
+            self.tree.continuations(&[]).unwrap(),
+        )?;
+        Ok(tokens)
+    }
+
+    fn push_line_tokens(
+        tokens: &mut Vec<String>,
+        board: &Board,
+        mut nodes: &[GameNode],
+    ) -> Result<(), ChessError> {
+        let mut board = board.clone();
+        let mut needs_number = true;
+        while let Some(node) = nodes.first() {
+            needs_number = Self::push_move_tokens(tokens, &board, node, needs_number)?;
+            for variation in nodes[1..].iter() {
+                let start = tokens.len();
+                Self::push_move_tokens(tokens, &board, variation, true)?;
+                let mut variation_board = board.clone();
+                Self::play(&mut variation_board, variation)?;
+                Self::push_line_tokens(tokens, &variation_board, variation.children())?;
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+                needs_number = true;
+            }
+            Self::play(&mut board, node)?;
+            nodes = node.children();
+        }
+        Ok(())
+    }
+
+    // Pushes a move with its annotations and tells whether the next move needs a number
+    fn push_move_tokens(
+        tokens: &mut Vec<String>,
+        board: &Board,
+        node: &GameNode,
+        needs_number: bool,
+    ) -> Result<bool, ChessError> {
+        for comment in node.comments_before.iter() {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        let record = node.record();
+        if board.side_to_move().is_white() {
+            tokens.push(format!("{}.", board.fullmove_number()));
+        } else if needs_number || !node.comments_before.is_empty() {
+            tokens.push(format!("{}...", board.fullmove_number()));
+        }
+        let mut san = board.to_san(&record.from, &record.to, record.promoted_to)?;
+        if let Some(suffix) = &node.suffix {
+            san.push_str(suffix);
+        }
+        tokens.push(san);
+        for nag in node.nags.iter() {
+            tokens.push(format!("${}", nag));
+        }
+        for comment in node.comments.iter() {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        Ok(!node.comments.is_empty())
+    }
+
+    fn play(board: &mut Board, node: &GameNode) -> Result<(), ChessError> {
+        let record = node.record();
+        board.simple_move(&record.from, &record.to, record.promoted_to)
+    }
+
+    fn wrap_tokens(tokens: &[String]) -> String {
+        let mut lines = vec![String::new()];
+        for token in tokens {
+            let line = lines.last_mut().unwrap();
+            if line.is_empty() {
+                line.push_str(token);
+            } else if line.len() + 1 + token.len() <= MAX_LINE_LENGTH {
+                line.push(' ');
+                line.push_str(token);
+            } else {
+                lines.push(token.clone());
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, ChessError, Game};
+
+    #[test]
+    fn test_pgn_export() -> Result<(), ChessError> {
+        let mut game = Game::new();
+        game.set_tag("White", "Morphy, Paul");
+        game.set_tag("Annotator", "\"Anon\"");
+        game.add_pgn_moves("1. e4 e5 2. Nf3 d6")?;
+        assert_eq!(
+            game.to_pgn()?,
+            "[Event \"?\"]\n\
+             [Site \"?\"]\n\
+             [Date \"????.??.??\"]\n\
+             [Round \"?\"]\n\
+             [White \"Morphy, Paul\"]\n\
+             [Black \"?\"]\n\
+             [Result \"*\"]\n\
+             [Annotator \"\\\"Anon\\\"\"]\n\
+             \n\
+             1. e4 e5 2. Nf3 d6 *\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_export_result() -> Result<(), ChessError> {
+        let mut game = Game::new();
+        game.add_pgn_moves("1. f3 e5 2. g4 Qh4")?;
+        assert_eq!(game.result(), "0-1");
+        assert!(game
+            .to_pgn()?
+            .ends_with("[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n"));
+
+        let mut game = Game::new();
+        game.set_tag("Result", "1/2-1/2");
+        assert!(game.to_pgn()?.ends_with("\n\n1/2-1/2\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_export_from_fen() -> Result<(), ChessError> {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let mut game = Game::from_board(Board::from_fen(fen)?)?;
+        game.add_pgn_moves("1... c5 2. Nf3")?;
+        let pgn = game.to_pgn()?;
+        assert!(pgn.contains(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen)));
+        assert!(pgn.ends_with("\n\n1... c5 2. Nf3 *\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_export_line_wrapping() -> Result<(), ChessError> {
+        let mut game = Game::new();
+        game.add_pgn_moves(
+            "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 \
+             8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 11. Nbd2 Bb7 12. Bc2 Re8",
+        )?;
+        let pgn = game.to_pgn()?;
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() <= 80));
+        assert_eq!(
+            movetext.split_whitespace().collect::<Vec<_>>().join(" "),
+            "1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 \
+             8. c3 O-O 9. h3 Nb8 10. d4 Nbd7 11. Nbd2 Bb7 12. Bc2 Re8 *"
+        );
+        Ok(())
+    }
+}
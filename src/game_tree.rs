@@ -0,0 +1,285 @@
+use std::time::Duration;
+
+use chess_pgn_parser::Square;
+
+use crate::{Board, ChessError, MoveRecord, PieceKind, PositionCursor};
+
+// A move in a game tree with the moves that can follow it. The first child continues the
+// main line, the others are alternatives to it.
+#[derive(Debug, Clone)]
+pub struct GameNode {
+    record: MoveRecord,
+    children: Vec<GameNode>,
+    // PGN annotations: comments written before and after the move, a "!?" style suffix and
+    // numeric annotation glyphs ($n)
+    pub comments_before: Vec<String>,
+    pub comments: Vec<String>,
+    pub suffix: Option<String>,
+    pub nags: Vec<u8>,
+}
+
+impl GameNode {
+    fn new(record: MoveRecord) -> GameNode {
+        GameNode {
+            record,
+            children: Vec::new(),
+            comments_before: Vec::new(),
+            comments: Vec::new(),
+            suffix: None,
+            nags: Vec::new(),
+        }
+    }
+
+    pub fn record(&self) -> &MoveRecord {
+        &self.record
+    }
+
+    pub fn children(&self) -> &[GameNode] {
+        &self.children
+    }
+
+    // The value of a command annotation in the comments, like "0:05:00" for [%clk 0:05:00]
+    pub fn command(&self, name: &str) -> Option<&str> {
+        let prefix = format!("[%{} ", name);
+        self.comments.iter().find_map(|comment| {
+            let start = comment.find(&prefix)? + prefix.len();
+            let len = comment[start..].find(']')?;
+            Some(comment[start..start + len].trim())
+        })
+    }
+
+    // Time left on the clock after the move, from [%clk h:mm:ss]
+    pub fn clock(&self) -> Option<Duration> {
+        let mut seconds = 0.0;
+        for part in self.command("clk")?.split(':') {
+            seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+        }
+        Some(Duration::from_secs_f64(seconds))
+    }
+
+    // Engine evaluation after the move, from [%eval 0.17] or [%eval #-3]
+    pub fn eval(&self) -> Option<&str> {
+        self.command("eval")
+    }
+}
+
+// A tree of moves from a starting board for analysis, where any position can have several
+// continuations. Nodes are addressed by paths: the child index to take at each ply, so that
+// [0, 0, 1] is the alternative to the main line's second reply.
+#[derive(Clone)]
+pub struct GameTree {
+    board: Board,
+    children: Vec<GameNode>,
+    // Comments of a game without moves
+    pub comments: Vec<String>,
+}
+
+impl Default for GameTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameTree {
+    pub fn new() -> GameTree {
+        Self::from_board(Board::new())
+    }
+
+    pub fn from_board(board: Board) -> GameTree {
+        GameTree {
+            board,
+            children: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    pub fn starting_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn node(&self, path: &[usize]) -> Option<&GameNode> {
+        let (last, parent) = path.split_last()?;
+        self.continuations(parent)?.get(*last)
+    }
+
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut GameNode> {
+        let (last, parent) = path.split_last()?;
+        self.continuations_mut(parent)?.get_mut(*last)
+    }
+
+    // The moves that can be played from the position reached by `path`, main line first
+    pub fn continuations(&self, path: &[usize]) -> Option<&[GameNode]> {
+        let mut children = &self.children;
+        for index in path {
+            children = &children.get(*index)?.children;
+        }
+        Some(children)
+    }
+
+    fn continuations_mut(&mut self, path: &[usize]) -> Option<&mut Vec<GameNode>> {
+        let mut children = &mut self.children;
+        for index in path {
+            children = &mut children.get_mut(*index)?.children;
+        }
+        Some(children)
+    }
+
+    // The path following the main line from `path` to its end
+    pub fn mainline(&self, path: &[usize]) -> Vec<usize> {
+        let mut mainline = path.to_vec();
+        while let Some(children) = self.continuations(&mainline) {
+            if children.is_empty() {
+                break;
+            }
+            mainline.push(0);
+        }
+        mainline
+    }
+
+    pub fn board_at(&self, path: &[usize]) -> Result<Board, ChessError> {
+        let mut board = self.board.clone();
+        let mut children = &self.children;
+        for index in path {
+            let node = children
+                .get(*index)
+                .ok_or_else(|| ChessError::InvalidTreePath(path.to_vec()))?;
+            board.simple_move(&node.record.from, &node.record.to, node.record.promoted_to)?;
+            children = &node.children;
+        }
+        Ok(board)
+    }
+
+    // Plays a move after `path` and returns the path to it. A move that is already there is
+    // not added twice.
+    pub fn add_move(
+        &mut self,
+        path: &[usize],
+        from: &Square,
+        to: &Square,
+        promoted_to: Option<PieceKind>,
+    ) -> Result<Vec<usize>, ChessError> {
+        let mut board = self.board_at(path)?;
+        board.simple_move(from, to, promoted_to)?;
+        Ok(self.add_record(path, board.move_list().last().unwrap().clone()))
+    }
+
+    // Adds a move already played on the board at `path`, without checking it again
+    pub(crate) fn add_record(&mut self, path: &[usize], record: MoveRecord) -> Vec<usize> {
+        let children = self.continuations_mut(path).unwrap();
+        let index = match children.iter().position(|node| node.record == record) {
+            Some(index) => index,
+            None => {
+                children.push(GameNode::new(record));
+                children.len() - 1
+            }
+        };
+        let mut new_path = path.to_vec();
+        new_path.push(index);
+        new_path
+    }
+
+    // Makes the line through `path` the main line at every ply, returning its new path
+    pub fn promote_variation(&mut self, path: &[usize]) -> Result<Vec<usize>, ChessError> {
+        if self.node(path).is_none() {
+            return Err(ChessError::InvalidTreePath(path.to_vec()));
+        }
+        for (ply, index) in path.iter().enumerate() {
+            let children = self.continuations_mut(&vec![0; ply]).unwrap();
+            let node = children.remove(*index);
+            children.insert(0, node);
+        }
+        Ok(vec![0; path.len()])
+    }
+
+    // Removes the move at `path` together with everything played after it
+    pub fn delete_variation(&mut self, path: &[usize]) -> Result<GameNode, ChessError> {
+        let invalid_path = || ChessError::InvalidTreePath(path.to_vec());
+        let (last, parent) = path.split_last().ok_or_else(invalid_path)?;
+        let children = self.continuations_mut(parent).ok_or_else(invalid_path)?;
+        if *last >= children.len() {
+            return Err(invalid_path());
+        }
+        Ok(children.remove(*last))
+    }
+
+    // A cursor over the positions leading to the end of `path`, through whichever
+    // variations it takes
+    pub fn position_cursor(&self, path: &[usize]) -> Result<PositionCursor<'_>, ChessError> {
+        Ok(PositionCursor::from_owned(self.board_at(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChessError, Color, GameTree, Square, UniquePiece};
+
+    fn sample_tree() -> Result<GameTree, ChessError> {
+        // 1. e4 e5 (1... c5 2. Nf3) (1... e6) 2. Nf3
+        let mut tree = GameTree::new();
+        let e4 = tree.add_move(&[], &Square::E2, &Square::E4, None)?;
+        let e5 = tree.add_move(&e4, &Square::E7, &Square::E5, None)?;
+        tree.add_move(&e5, &Square::G1, &Square::F3, None)?;
+        let c5 = tree.add_move(&e4, &Square::C7, &Square::C5, None)?;
+        tree.add_move(&c5, &Square::G1, &Square::F3, None)?;
+        tree.add_move(&e4, &Square::E7, &Square::E6, None)?;
+        Ok(tree)
+    }
+
+    #[test]
+    fn test_add_moves() -> Result<(), ChessError> {
+        let mut tree = sample_tree()?;
+        assert_eq!(tree.continuations(&[0]).unwrap().len(), 3);
+        assert_eq!(tree.mainline(&[]), vec![0, 0, 0]);
+        assert_eq!(tree.mainline(&[0, 1]), vec![0, 1, 0]);
+        assert_eq!(tree.node(&[0, 2]).unwrap().record().to, Square::E6);
+
+        // Playing a move that is already in the tree reuses it
+        assert_eq!(
+            tree.add_move(&[0], &Square::C7, &Square::C5, None)?,
+            vec![0, 1]
+        );
+        assert!(tree.add_move(&[0], &Square::E2, &Square::E4, None).is_err());
+        assert!(tree.add_move(&[3], &Square::E2, &Square::E4, None).is_err());
+
+        let board = tree.board_at(&[0, 1, 0])?;
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_promote_and_delete_variation() -> Result<(), ChessError> {
+        let mut tree = sample_tree()?;
+        assert_eq!(tree.promote_variation(&[0, 1, 0])?, vec![0, 0, 0]);
+        assert_eq!(tree.node(&[0, 0]).unwrap().record().to, Square::C5);
+        assert_eq!(tree.node(&[0, 1]).unwrap().record().to, Square::E5);
+        assert_eq!(tree.node(&[0, 2]).unwrap().record().to, Square::E6);
+
+        let deleted = tree.delete_variation(&[0, 1])?;
+        assert_eq!(deleted.record().to, Square::E5);
+        assert_eq!(deleted.children().len(), 1);
+        assert_eq!(tree.node(&[0, 1]).unwrap().record().to, Square::E6);
+        assert!(tree.delete_variation(&[0, 5]).is_err());
+        assert!(tree.promote_variation(&[1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cursor_follows_variation() -> Result<(), ChessError> {
+        let tree = sample_tree()?;
+        let mut cursor = tree.position_cursor(&[0, 1, 0])?;
+        assert_eq!(cursor.ply(), 3);
+        let pawn_squares: Vec<_> = (&mut cursor)
+            .rev()
+            .map(|pos| pos[&(UniquePiece::CPawn, Color::Black)].clone())
+            .collect();
+        assert_eq!(pawn_squares, vec![Square::C5, Square::C7, Square::C7]);
+        assert_eq!(
+            cursor.seek(3).unwrap()[&(UniquePiece::KKnight, Color::White)],
+            Square::F3
+        );
+        Ok(())
+    }
+}
@@ -1,35 +1,105 @@
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 
 pub use chess_pgn_parser::{
     parse_move_sequence, peggler::ParseError, File, Move::BasicMove, Move::CastleKingside,
     Move::CastleQueenside, Piece, Rank, Square,
 };
 
+mod bitboard;
+mod castling_rights;
+mod chess960;
+mod chess_move;
+mod color;
+mod evaluation;
+mod fen;
+mod game;
+mod game_tree;
+mod move_record;
+mod perft;
+mod pgn;
+mod pgn_reader;
 mod piece_data;
+mod piece_kind;
 mod position_cursor;
 mod rules;
+mod san;
+mod search;
+mod square_index;
+mod uci;
+mod zobrist;
 
+use bitboard::Bitboards;
+pub use castling_rights::{CastlingFiles, CastlingRights};
+pub use chess_move::Move;
+pub use color::Color;
+pub use fen::STARTING_FEN;
+pub use game::Game;
+pub use game_tree::{GameNode, GameTree};
+pub use move_record::MoveRecord;
+pub use pgn_reader::{PgnReadError, PgnReader};
 use piece_data::PieceData;
-use position_cursor::PositionCursor;
+pub use piece_kind::PieceKind;
+pub use position_cursor::PositionCursor;
 pub use rules::UniquePiece;
+pub use search::{SearchLimits, SearchResult, MATE_SCORE};
 
 use std::convert::From;
 use std::fmt;
 
-use crate::rules::{BishopRules, KnightRules, QueenRules, RookRules};
-
-type Position = HashMap<(UniquePiece, bool), Square>;
+pub type Position = HashMap<(UniquePiece, Color), Square>;
 
+#[derive(Clone)]
 pub struct Board {
     pieces: Vec<PieceData>,
+    castling_rights: CastlingRights,
+    castling_files: CastlingFiles,
+    // Chess960 boards write castling as the king taking its own rook
+    chess960: bool,
+    en_passant_square: Option<Square>,
+    side_to_move: Color,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    position_keys: Vec<u64>,
+    starting_fen: String,
+    move_list: Vec<MoveRecord>,
+    // Moves taken back with undo, the next one to redo last
+    redo_list: Vec<MoveRecord>,
+    // Placement of `pieces` for fast lookups and attack detection, rebuilt when stale
+    bitboards: RefCell<Bitboards>,
+    stale_bitboards: Cell<bool>,
+    hash: Cell<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Outcome {
+    Checkmate { winner: Color },
+    Stalemate,
+    InsufficientMaterial,
+    FivefoldRepetition,
+    SeventyFiveMoveRule,
+    // Draws that a player may claim, but that don't end the game on their own
+    ThreefoldRepetition,
+    FiftyMoveRule,
 }
 
 #[derive(Debug)]
 pub enum ChessError {
-    IlegalMove(UniquePiece, Square, Square),
+    IlegalMove(PieceKind, Color, Square, Square),
     PgnParseError,
+    FenParseError(String),
     IncorrectMoveParameters,
     UnDisambiguable,
+    UnreachableSquare(PieceKind, Color, Square),
+    EmptySquare(Square),
+    OutOfTurn(PieceKind, Color),
+    InvalidPromotion(Square, Option<PieceKind>),
+    InvalidTreePath(Vec<usize>),
+    InvalidSan(String),
+    InvalidChess960Index(u16),
+    Chess960CastlingRequired,
+    InvalidUci(String),
+    IoError(std::io::Error),
 }
 
 impl From<ParseError> for ChessError {
@@ -41,235 +111,721 @@ impl From<ParseError> for ChessError {
 impl fmt::Display for ChessError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Self::IlegalMove(piece, from, to) => {
+            Self::IlegalMove(piece, color, from, to) => {
                 write!(
                     f,
-                    "Invalid move. Piece: {:?}, from: {:?}, to: {:?}",
-                    piece, from, to
+                    "Invalid move. Piece: {:?} {:?}, from: {:?}, to: {:?}",
+                    color, piece, from, to
                 )
             }
             Self::PgnParseError => write!(f, "Failed to parse pgn string"),
+            Self::FenParseError(reason) => write!(f, "Failed to parse fen string: {}", reason),
             Self::IncorrectMoveParameters => write!(f, "Incorrect move parameters"),
             Self::UnDisambiguable => write!(f, "Unable to disambiguate 'from' square"),
+            Self::UnreachableSquare(piece, color, to) => {
+                write!(f, "No {:?} {:?} can legally move to {:?}", color, piece, to)
+            }
+            Self::EmptySquare(square) => write!(f, "No piece at square {:?}", square),
+            Self::OutOfTurn(piece, color) => write!(
+                f,
+                "Out of turn move. Piece: {:?} {:?}, it is {} to move",
+                color,
+                piece,
+                if color.is_white() { "black" } else { "white" }
+            ),
+            Self::InvalidPromotion(to, promoted_to) => write!(
+                f,
+                "Invalid promotion. To: {:?}, promoted to: {:?}",
+                to, promoted_to
+            ),
+            Self::InvalidTreePath(path) => write!(f, "No move at tree path {:?}", path),
+            Self::InvalidSan(san) => write!(f, "No legal move matches '{}'", san),
+            Self::InvalidChess960Index(index) => {
+                write!(
+                    f,
+                    "No Chess960 setup has index {}, expected 0 to 959",
+                    index
+                )
+            }
+            Self::Chess960CastlingRequired => write!(
+                f,
+                "Castling from non-standard squares must be written as the king taking its rook"
+            ),
+            Self::InvalidUci(uci) => write!(f, "No legal move matches '{}'", uci),
+            Self::IoError(error) => write!(f, "Failed to read input: {}", error),
         }
     }
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Board {
     pub fn new() -> Board {
-        Board {
-            pieces: Self::create_initial_pieces(),
-        }
-    }
-
-    fn create_initial_pieces() -> Vec<PieceData> {
-        vec![
-            PieceData::new_pawn(File::A, true),
-            PieceData::new_pawn(File::B, true),
-            PieceData::new_pawn(File::C, true),
-            PieceData::new_pawn(File::D, true),
-            PieceData::new_pawn(File::E, true),
-            PieceData::new_pawn(File::F, true),
-            PieceData::new_pawn(File::G, true),
-            PieceData::new_pawn(File::H, true),
-            PieceData::new_pawn(File::A, false),
-            PieceData::new_pawn(File::B, false),
-            PieceData::new_pawn(File::C, false),
-            PieceData::new_pawn(File::D, false),
-            PieceData::new_pawn(File::E, false),
-            PieceData::new_pawn(File::F, false),
-            PieceData::new_pawn(File::G, false),
-            PieceData::new_pawn(File::H, false),
-            PieceData::new_rook(File::A, true),
-            PieceData::new_rook(File::H, true),
-            PieceData::new_rook(File::A, false),
-            PieceData::new_rook(File::H, false),
-            PieceData::new_knight(File::B, true),
-            PieceData::new_knight(File::G, true),
-            PieceData::new_knight(File::B, false),
-            PieceData::new_knight(File::G, false),
-            PieceData::new_bishop(File::C, true),
-            PieceData::new_bishop(File::F, true),
-            PieceData::new_bishop(File::C, false),
-            PieceData::new_bishop(File::F, false),
-            PieceData::new_queen(true),
-            PieceData::new_queen(false),
-            PieceData::new_king(true),
-            PieceData::new_king(false),
-        ]
+        Self::from_fen(STARTING_FEN).expect("starting position is valid")
     }
 
+    // Number of positions in the game so far, including the starting one
     fn position_count(&self) -> usize {
-        self.pieces
-            .first()
-            .expect("board can't be empty")
-            .square_hist
-            .len()
+        self.move_list.len() + 1
     }
 
-    pub fn position_cursor(&self) -> PositionCursor {
+    pub fn position_cursor(&self) -> PositionCursor<'_> {
         PositionCursor::new(self)
     }
 
+    // Finds the one piece of the given type that can legally move to `to`, narrowed down by
+    // whatever part of the 'from' square the PGN move specified
     fn disambiguate_from_square(
         &self,
-        piece: Piece,
-        white: bool,
+        piece: PieceKind,
+        color: Color,
         from: &Square,
         to: &Square,
     ) -> Result<Square, ChessError> {
-        let piece_data_list = self.get_all_live_piece_data_with_type(piece, white);
-        if let Some(rank) = from.rank() {
-            if let Some(piece_data) = piece_data_list
-                .iter()
-                .find(|p| p.curr_square().unwrap().rank().unwrap() == rank)
-            {
-                return Ok(piece_data.curr_square().unwrap().clone());
-            }
-        } else if let Some(file) = from.file() {
-            if let Some(piece_data) = piece_data_list
-                .iter()
-                .find(|p| p.curr_square().unwrap().file().unwrap() == file)
-            {
-                return Ok(piece_data.curr_square().unwrap().clone());
-            }
-        } else {
-            for piece_data in piece_data_list {
-                let valid_squares = piece_data.behavior.get_valid_squares(piece_data, &self);
-                if valid_squares.iter().find(|s| *s == to).is_some() {
-                    return Ok(piece_data.curr_square().unwrap().clone());
-                }
-            }
+        let candidates: Vec<&Square> = self
+            .get_all_live_piece_data_with_type(piece, color)
+            .into_iter()
+            .filter(|p| {
+                let square = p.curr_square().unwrap();
+                from.file().is_none_or(|file| square.file() == Some(file))
+                    && from.rank().is_none_or(|rank| square.rank() == Some(rank))
+                    && p.behavior.get_valid_squares(p, self).contains(to)
+            })
+            .map(|p| p.curr_square().unwrap())
+            .collect();
+        match candidates.as_slice() {
+            [square] => Ok((*square).clone()),
+            [] => Err(ChessError::UnreachableSquare(piece, color, to.clone())),
+            _ => Err(ChessError::UnDisambiguable),
         }
-        Err(ChessError::UnDisambiguable)
     }
 
     fn add_basic_move(
         &mut self,
-        piece: Piece,
-        white: bool,
+        piece: PieceKind,
+        color: Color,
         to: &Square,
         from: &Square,
         is_capture: bool,
-        promoted_to: Option<Piece>,
+        promoted_to: Option<PieceKind>,
     ) -> Result<(), ChessError> {
-        if let Some(captured_piece_data) = self.get_mut_piece_data_at_square(to) {
+        let known_from = from
+            .get_known()
+            .ok_or(ChessError::IncorrectMoveParameters)?;
+        let moved_piece = self
+            .get_piece_data_at_square(&known_from)
+            .ok_or(ChessError::IncorrectMoveParameters)?
+            .piece;
+        let mut record = MoveRecord {
+            from: known_from.clone(),
+            to: to.clone(),
+            piece: moved_piece,
+            color,
+            captured_piece: None,
+            en_passant: false,
+            promoted_to,
+            castling: false,
+            prev_castling_rights: self.castling_rights,
+            prev_en_passant_square: self.en_passant_square.clone(),
+            prev_halfmove_clock: self.halfmove_clock,
+            prev_fullmove_number: self.fullmove_number,
+        };
+        self.toggle_state_hash();
+        if let Some(captured_piece) = self.remove_piece(to) {
             assert!(is_capture);
-            captured_piece_data.capture();
+            record.captured_piece = Some(captured_piece);
         }
-        let known_from = match from.get_known() {
-            Some(known_from) => known_from,
-            None => self.disambiguate_from_square(piece, white, from, to)?,
-        };
-        let piece_data = self
-            .get_mut_piece_data_at_square(&known_from)
+        let en_passant_square = self.en_passant_square.take();
+        if piece == PieceKind::Pawn {
+            if en_passant_square.as_ref() == Some(to) {
+                let passed_square = Square::new_known(
+                    to.file().ok_or(ChessError::IncorrectMoveParameters)?,
+                    known_from
+                        .rank()
+                        .ok_or(ChessError::IncorrectMoveParameters)?,
+                );
+                if let Some(passed_piece) = self.remove_piece(&passed_square) {
+                    record.captured_piece = Some(passed_piece);
+                    record.en_passant = true;
+                }
+            }
+            self.en_passant_square = Self::get_skipped_square(&known_from, to);
+        }
+        if piece == PieceKind::Pawn || record.captured_piece.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if piece == PieceKind::King {
+            self.castling_rights.revoke_all(color);
+        }
+        let files = self.castling_files;
+        self.castling_rights.revoke_for_square(&known_from, &files);
+        self.castling_rights.revoke_for_square(to, &files);
+        self.side_to_move = !color;
+        self.move_piece(&known_from, to)
             .ok_or(ChessError::IncorrectMoveParameters)?;
-        piece_data.move_unchecked(to.clone());
         if let Some(promotion) = promoted_to {
             match promotion {
-                chess_pgn_parser::Piece::Rook => piece_data.behavior = Box::new(RookRules),
-                chess_pgn_parser::Piece::Knight => piece_data.behavior = Box::new(KnightRules),
-                chess_pgn_parser::Piece::Bishop => piece_data.behavior = Box::new(BishopRules),
-                chess_pgn_parser::Piece::Queen => piece_data.behavior = Box::new(QueenRules),
+                PieceKind::Rook | PieceKind::Knight | PieceKind::Bishop | PieceKind::Queen => {
+                    self.set_piece_kind(to, promotion);
+                }
                 _ => return Err(ChessError::IncorrectMoveParameters),
             }
         }
+        if color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.toggle_state_hash();
+        self.move_list.push(record);
+        self.record_position();
         Ok(())
     }
 
-    fn add_castle_move(
-        &mut self,
-        rank: Rank,
-        old_rook_file: File,
-        new_king_file: File,
-        new_rook_file: File,
-    ) -> Result<(), ChessError> {
-        let old_king_square = Square::new_known(File::E, rank);
+    fn add_castle_move(&mut self, king_side: bool, color: Color) -> Result<(), ChessError> {
+        let rank = if color.is_white() { Rank::R1 } else { Rank::R8 };
+        let (new_king_file, new_rook_file) = Self::get_castled_files(king_side);
+        let old_king_square = Square::new_known(self.castling_files.king, rank);
+        let old_rook_square = Square::new_known(self.castling_files.rook(king_side), rank);
         let new_king_square = Square::new_known(new_king_file, rank);
-        let old_rook_square = Square::new_known(old_rook_file, rank);
         let new_rook_square = Square::new_known(new_rook_file, rank);
-        let king_piece_data = self
-            .get_mut_piece_data_at_square(&old_king_square)
-            .ok_or(ChessError::IncorrectMoveParameters)?;
-        king_piece_data.move_unchecked(new_king_square);
-
-        let rook_data = self
-            .get_mut_piece_data_at_square(&old_rook_square)
-            .ok_or(ChessError::IncorrectMoveParameters)?;
-        rook_data.move_unchecked(new_rook_square);
+        if !self.can_castle(king_side, color) {
+            return Err(ChessError::IlegalMove(
+                PieceKind::King,
+                color,
+                old_king_square,
+                new_king_square,
+            ));
+        }
+        self.move_list.push(MoveRecord {
+            from: old_king_square.clone(),
+            to: if self.chess960 {
+                old_rook_square.clone()
+            } else {
+                new_king_square.clone()
+            },
+            piece: UniquePiece::King,
+            color,
+            captured_piece: None,
+            en_passant: false,
+            promoted_to: None,
+            castling: true,
+            prev_castling_rights: self.castling_rights,
+            prev_en_passant_square: self.en_passant_square.clone(),
+            prev_halfmove_clock: self.halfmove_clock,
+            prev_fullmove_number: self.fullmove_number,
+        });
+        self.toggle_state_hash();
+        self.castling_rights.revoke_all(color);
+        self.en_passant_square = None;
+        self.side_to_move = !color;
+        self.halfmove_clock += 1;
+        if color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.move_castling_pieces(
+            color,
+            (&old_king_square, &new_king_square),
+            (&old_rook_square, &new_rook_square),
+        )
+        .ok_or(ChessError::IncorrectMoveParameters)?;
+        self.toggle_state_hash();
+        self.record_position();
         Ok(())
     }
 
+    // Files of the king and rook after castling, the same in standard chess and Chess960
+    fn get_castled_files(king_side: bool) -> (File, File) {
+        if king_side {
+            (File::G, File::F)
+        } else {
+            (File::C, File::D)
+        }
+    }
+
+    // Moves king and rook as (from, to) pairs. In Chess960 either may land on the square the
+    // other leaves, so both are lifted before either is put down.
+    fn move_castling_pieces(
+        &mut self,
+        color: Color,
+        king: (&Square, &Square),
+        rook: (&Square, &Square),
+    ) -> Option<()> {
+        let king_piece = self.remove_piece(king.0)?;
+        let rook_piece = self.remove_piece(rook.0)?;
+        self.restore_piece(king_piece, color, king.1)?;
+        self.restore_piece(rook_piece, color, rook.1)
+    }
+
+    // The color whose turn it is to move
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    // Plays a move after checking it is the mover's turn and the move is legal
     pub fn simple_move(
         &mut self,
         from: &Square,
         to: &Square,
-        promoted_to: Option<Piece>,
+        promoted_to: Option<PieceKind>,
     ) -> Result<(), ChessError> {
         let piece_data = self
             .get_piece_data_at_square(from)
-            .ok_or(ChessError::IncorrectMoveParameters)?;
-        let piece = piece_data.piece;
-        let white = piece_data.white;
+            .ok_or_else(|| ChessError::EmptySquare(from.clone()))?;
+        let kind = piece_data.behavior.kind();
+        let color = piece_data.color;
+        if color != self.side_to_move {
+            return Err(ChessError::OutOfTurn(kind, color));
+        }
+        // Castling is accepted in either notation, whichever one the board writes
+        if let Some(king_side) = self.get_castling_side(piece_data, to) {
+            return self.castle(king_side, color);
+        }
+        if !piece_data
+            .behavior
+            .get_valid_squares(piece_data, self)
+            .contains(to)
+        {
+            return Err(ChessError::IlegalMove(
+                kind,
+                color,
+                from.clone(),
+                to.clone(),
+            ));
+        }
+        let last_rank = if color.is_white() { Rank::R8 } else { Rank::R1 };
+        let promotes = kind == PieceKind::Pawn && to.rank() == Some(last_rank);
+        let valid_promotion = match promoted_to {
+            None => !promotes,
+            Some(PieceKind::Pawn) | Some(PieceKind::King) => false,
+            Some(_) => promotes,
+        };
+        if !valid_promotion {
+            return Err(ChessError::InvalidPromotion(to.clone(), promoted_to));
+        }
+        self.play_unchecked(from, to, promoted_to)
+    }
+
+    // Plays a move from `legal_moves` without checking it again
+    pub(crate) fn play_legal_move(&mut self, m: &Move) -> Result<(), ChessError> {
+        self.play_unchecked(&m.from(), &m.to(), m.promotion())
+    }
+
+    fn play_unchecked(
+        &mut self,
+        from: &Square,
+        to: &Square,
+        promoted_to: Option<PieceKind>,
+    ) -> Result<(), ChessError> {
+        let piece_data = self
+            .get_piece_data_at_square(from)
+            .ok_or_else(|| ChessError::EmptySquare(from.clone()))?;
+        let color = piece_data.color;
+        let kind = piece_data.behavior.kind();
+        if let Some(king_side) = self.get_castling_side(piece_data, to) {
+            return self.castle(king_side, color);
+        }
         let is_capture = self.get_piece_data_at_square(to).is_some();
-        self.add_basic_move(
-            Self::unique_to_piece(piece),
-            white,
-            to,
-            from,
-            is_capture,
-            promoted_to,
-        )?;
+        self.add_basic_move(kind, color, to, from, is_capture, promoted_to)?;
+        self.redo_list.clear();
         Ok(())
     }
 
-    pub fn castle(&mut self, king_side: bool, white: bool) -> Result<(), ChessError> {
-        let rank = if white {
-            Rank::R1
-        } else {
-            Rank::R8
+    pub fn castle(&mut self, king_side: bool, color: Color) -> Result<(), ChessError> {
+        if color != self.side_to_move {
+            return Err(ChessError::OutOfTurn(PieceKind::King, color));
+        }
+        self.add_castle_move(king_side, color)?;
+        self.redo_list.clear();
+        Ok(())
+    }
+
+    // Takes back the last move, returning it, or None at the starting position. The board is
+    // left untouched if the move can't be taken back.
+    pub fn undo(&mut self) -> Option<MoveRecord> {
+        let record = self.move_list.last()?;
+        let castling_squares = self.get_undo_castling_squares(record);
+        // Everything that could fail is checked before anything changes
+        let movers_in_place = match &castling_squares {
+            Some((king, rook)) => {
+                self.is_piece_at(&king.0, UniquePiece::King, record.color)
+                    && self.get_piece_data_at_square(&rook.0).is_some_and(|p| {
+                        p.behavior.kind() == PieceKind::Rook && p.color == record.color
+                    })
+            }
+            None if record.castling => false,
+            None => self.is_piece_at(&record.to, record.piece, record.color),
         };
-        let (old_rook_file, new_king_file, new_rook_file) = match king_side {
-            true => (File::H, File::G, File::F),
-            false => (File::A, File::C, File::D),
+        let captured_off_board = match (record.captured_piece, record.captured_square()) {
+            (Some(captured_piece), Some(_)) => self.pieces.iter().any(|p| {
+                p.piece == captured_piece && p.color == !record.color && p.curr_square().is_none()
+            }),
+            (Some(_), None) => false,
+            (None, _) => true,
         };
-        self.add_castle_move(rank, old_rook_file, new_king_file, new_rook_file)?;
+        if !movers_in_place || !captured_off_board {
+            return None;
+        }
+
+        let record = self.move_list.pop()?;
+        self.toggle_state_hash();
+        match castling_squares {
+            Some((king, rook)) => {
+                self.move_castling_pieces(record.color, (&king.0, &king.1), (&rook.0, &rook.1))?
+            }
+            None => self.move_piece(&record.to, &record.from)?,
+        }
+        if record.promoted_to.is_some() {
+            self.set_piece_kind(&record.from, PieceKind::Pawn)?;
+        }
+        if let (Some(captured_piece), Some(captured_square)) =
+            (record.captured_piece, record.captured_square())
+        {
+            self.restore_piece(captured_piece, !record.color, &captured_square)?;
+        }
+        self.castling_rights = record.prev_castling_rights;
+        self.en_passant_square = record.prev_en_passant_square.clone();
+        self.halfmove_clock = record.prev_halfmove_clock;
+        self.fullmove_number = record.prev_fullmove_number;
+        self.side_to_move = record.color;
+        self.toggle_state_hash();
+        self.position_keys.pop();
+        self.redo_list.push(record.clone());
+        Some(record)
+    }
+
+    // For a castling record, the king's and the rook's squares as (now, before castling)
+    fn get_undo_castling_squares(
+        &self,
+        record: &MoveRecord,
+    ) -> Option<((Square, Square), (Square, Square))> {
+        if !record.castling {
+            return None;
+        }
+        let rank = record.from.rank()?;
+        let king_side = square_index::square_coords(&record.to)?.0
+            > square_index::square_coords(&record.from)?.0;
+        let (new_king_file, new_rook_file) = Self::get_castled_files(king_side);
+        Some((
+            (Square::new_known(new_king_file, rank), record.from.clone()),
+            (
+                Square::new_known(new_rook_file, rank),
+                Square::new_known(self.castling_files.rook(king_side), rank),
+            ),
+        ))
+    }
+
+    fn is_piece_at(&self, square: &Square, piece: UniquePiece, color: Color) -> bool {
+        self.get_piece_data_at_square(square)
+            .is_some_and(|p| p.piece == piece && p.color == color)
+    }
+
+    // Plays again the last move taken back with undo, returning it. The moves to redo are kept
+    // if it can't be played.
+    pub fn redo(&mut self) -> Option<MoveRecord> {
+        let record = self.redo_list.last()?.clone();
+        let mut redo_list = std::mem::take(&mut self.redo_list);
+        let played = self.simple_move(&record.from, &record.to, record.promoted_to);
+        if played.is_ok() {
+            redo_list.pop();
+        }
+        self.redo_list = redo_list;
+        played.ok()?;
+        Some(record)
+    }
+
+    pub fn starting_fen(&self) -> &str {
+        &self.starting_fen
+    }
+
+    // Moves played since the starting position, in order
+    pub fn move_list(&self) -> &[MoveRecord] {
+        &self.move_list
+    }
+
+    // A fresh board at the starting position, in the same castling notation
+    pub(crate) fn get_starting_board(&self) -> Result<Board, ChessError> {
+        let mut board = Board::from_fen(&self.starting_fen)?;
+        board.chess960 = self.chess960;
+        Ok(board)
+    }
+
+    // Replays the moves from the starting position to write each of them in SAN
+    pub fn san_moves(&self) -> Result<Vec<String>, ChessError> {
+        let mut board = self.get_starting_board()?;
+        let mut san_moves = Vec::new();
+        for record in self.move_list.iter() {
+            san_moves.push(board.to_san(&record.from, &record.to, record.promoted_to)?);
+            board.simple_move(&record.from, &record.to, record.promoted_to)?;
+        }
+        Ok(san_moves)
+    }
+
+    // The square a pawn skipped over if it advanced two squares, None otherwise
+    fn get_skipped_square(from: &Square, to: &Square) -> Option<Square> {
+        let (from_file, from_rank) = square_index::square_coords(from)?;
+        let (to_file, to_rank) = square_index::square_coords(to)?;
+        if from_file == to_file && (to_rank - from_rank).abs() == 2 {
+            from.new_with_offset(0, (to_rank - from_rank).signum())
+        } else {
+            None
+        }
+    }
+
+    pub fn en_passant_square(&self) -> Option<&Square> {
+        self.en_passant_square.as_ref()
+    }
+
+    fn is_en_passant(&self, piece_data: &PieceData, to: &Square) -> bool {
+        piece_data.behavior.kind() == PieceKind::Pawn
+            && self.en_passant_square.as_ref() == Some(to)
+            && self.get_piece_data_at_square(to).is_none()
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    // Reports how the game ended, if it did. Claimable draws are only reported when no
+    // other termination applies.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if !self.has_legal_moves() {
+            return if self.is_in_check(self.side_to_move) {
+                Some(Outcome::Checkmate {
+                    winner: !self.side_to_move,
+                })
+            } else {
+                Some(Outcome::Stalemate)
+            };
+        }
+        let repetitions = self.repetition_count();
+        if self.has_insufficient_material() {
+            Some(Outcome::InsufficientMaterial)
+        } else if repetitions >= 5 {
+            Some(Outcome::FivefoldRepetition)
+        } else if self.halfmove_clock >= 150 {
+            Some(Outcome::SeventyFiveMoveRule)
+        } else if repetitions >= 3 {
+            Some(Outcome::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(Outcome::FiftyMoveRule)
+        } else {
+            None
+        }
+    }
+
+    fn has_legal_moves(&self) -> bool {
+        self.pieces
+            .iter()
+            .filter(|p| p.color == self.side_to_move && p.curr_square().is_some())
+            .any(|p| !p.behavior.get_valid_squares(p, self).is_empty())
+    }
+
+    fn has_insufficient_material(&self) -> bool {
+        let mut minor_pieces = Vec::new();
+        for piece_data in self.pieces.iter() {
+            let square = match piece_data.curr_square() {
+                Some(square) => square,
+                None => continue,
+            };
+            match piece_data.behavior.kind() {
+                PieceKind::King => {}
+                PieceKind::Knight | PieceKind::Bishop => {
+                    minor_pieces.push((piece_data.behavior.kind(), square))
+                }
+                _ => return false,
+            }
+        }
+        if minor_pieces.len() <= 1 {
+            return true;
+        }
+
+        // Any number of bishops that all travel on the same square color can't mate
+        let square_colors: HashSet<Option<bool>> = minor_pieces
+            .iter()
+            .map(|(piece, square)| match piece {
+                PieceKind::Bishop => {
+                    square_index::square_coords(square).map(|(file, rank)| (file + rank) % 2 == 0)
+                }
+                _ => None,
+            })
+            .collect();
+        square_colors.len() == 1 && !square_colors.contains(&None)
+    }
+
+    // Number of times the current position has occurred, including now
+    pub fn repetition_count(&self) -> usize {
+        match self.position_keys.last() {
+            Some(curr_key) => self
+                .position_keys
+                .iter()
+                .filter(|key| *key == curr_key)
+                .count(),
+            None => 0,
+        }
+    }
+
+    // Moves the side to move, castling and en passant part of the hash in or out, around
+    // changes to them
+    fn toggle_state_hash(&self) {
+        self.hash.set(self.hash() ^ self.state_hash());
+    }
+
+    fn record_position(&mut self) {
+        let key = self.get_position_key();
+        self.position_keys.push(key);
+    }
+
+    fn piece_to_char(piece: PieceKind, color: Color) -> char {
+        let c = match piece {
+            PieceKind::Pawn => 'p',
+            PieceKind::Knight => 'n',
+            PieceKind::Bishop => 'b',
+            PieceKind::Rook => 'r',
+            PieceKind::Queen => 'q',
+            PieceKind::King => 'k',
+        };
+        if color.is_white() {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }
+
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    pub fn castling_files(&self) -> CastlingFiles {
+        self.castling_files
+    }
+
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    // Chooses how castling moves are written: as the king moving two files, or as the king
+    // taking its own rook like in Chess960. Either is accepted when playing moves. Castling from
+    // other than the standard squares can only be written the Chess960 way, since the king's
+    // destination may also be an ordinary king move.
+    pub fn set_chess960(&mut self, chess960: bool) -> Result<(), ChessError> {
+        if !chess960 && self.castling_files != CastlingFiles::STANDARD {
+            return Err(ChessError::Chess960CastlingRequired);
+        }
+        self.chess960 = chess960;
         Ok(())
     }
 
+    // The king and the castling rook must be on their initial squares, and every square either
+    // of them crosses or lands on must be empty but for the two of them. The king may not be
+    // in check, cross or land on an attacked square, looking past the castling rook since it
+    // may be screening the king's destination.
+    fn can_castle(&self, king_side: bool, color: Color) -> bool {
+        if !self.castling_rights.has(king_side, color) {
+            return false;
+        }
+        let rank = if color.is_white() { Rank::R1 } else { Rank::R8 };
+        let (new_king_file, new_rook_file) = Self::get_castled_files(king_side);
+        let index = |file| bitboard::square_index(&Square::new_known(file, rank)).unwrap();
+        let (king_index, rook_index) = (
+            index(self.castling_files.king),
+            index(self.castling_files.rook(king_side)),
+        );
+        let bitboards = self.bitboards();
+        let king_in_place = bitboards.pieces(PieceKind::King, color) & 1 << king_index != 0;
+        let rook_in_place = bitboards.pieces(PieceKind::Rook, color) & 1 << rook_index != 0;
+        if !king_in_place || !rook_in_place {
+            return false;
+        }
+        let occupied = bitboards.occupied() & !(1 << king_index) & !(1 << rook_index);
+        let king_path = bitboard::rank_span(king_index, index(new_king_file));
+        let rook_path = bitboard::rank_span(rook_index, index(new_rook_file));
+        if occupied & (king_path | rook_path) != 0 {
+            return false;
+        }
+        bitboard::to_indices(king_path)
+            .all(|square| bitboards.attackers(square, !color, occupied, 0) == 0)
+    }
+
+    // Squares the king moves to for every castling move currently available: its destination,
+    // or in Chess960 the rook it castles with
+    pub(crate) fn get_castling_squares(&self, color: Color) -> Vec<Square> {
+        let rank = if color.is_white() { Rank::R1 } else { Rank::R8 };
+        [true, false]
+            .iter()
+            .copied()
+            .filter(|king_side| self.can_castle(*king_side, color))
+            .map(|king_side| {
+                let file = if self.chess960 {
+                    self.castling_files.rook(king_side)
+                } else {
+                    Self::get_castled_files(king_side).0
+                };
+                Square::new_known(file, rank)
+            })
+            .collect()
+    }
+
+    // The wing a king move castles to, if it castles. Only a king on its castling square with
+    // the right to castle to that wing castles, written either as the king moving two files to
+    // its destination or, as Chess960 boards do, as the king taking its own rook.
+    pub(crate) fn get_castling_side(&self, piece_data: &PieceData, to: &Square) -> Option<bool> {
+        if piece_data.behavior.kind() != PieceKind::King {
+            return None;
+        }
+        let color = piece_data.color;
+        let rank = if color.is_white() { Rank::R1 } else { Rank::R8 };
+        let from = piece_data.curr_square()?;
+        if *from != Square::new_known(self.castling_files.king, rank) || to.rank() != Some(rank) {
+            return None;
+        }
+        let (from_file, _) = square_index::square_coords(from)?;
+        let (to_file, _) = square_index::square_coords(to)?;
+        let king_side = to_file > from_file;
+        let takes_own_rook = to.file() == Some(self.castling_files.rook(king_side))
+            && matches!(
+                self.get_piece_data_at_square(to),
+                Some(p) if p.color == color && p.behavior.kind() == PieceKind::Rook
+            );
+        let jumps_to_destination =
+            (to_file - from_file).abs() == 2 && matches!(to.file(), Some(File::G) | Some(File::C));
+        if (takes_own_rook || jumps_to_destination) && self.castling_rights.has(king_side, color) {
+            Some(king_side)
+        } else {
+            None
+        }
+    }
+
+    // Replays the moves in turn order starting from the current side to move. Move number
+    // tokens don't affect which side a move is attributed to.
     pub fn add_pgn_moves(&mut self, pgn_moves: &str) -> Result<(), ChessError> {
-        let game_moves = parse_move_sequence(pgn_moves)?;
+        let game_moves = parse_move_sequence(&pgn::letter_castling(pgn_moves))?;
         for game_move in game_moves.moves.iter() {
+            let color = self.side_to_move;
             match game_move.move_.move_ {
                 BasicMove {
                     piece,
                     ref to,
                     ref from,
-                    is_capture,
                     promoted_to,
+                    ..
                 } => {
-                    self.add_basic_move(
-                        piece,
-                        game_move.number.is_some(),
-                        to,
-                        from,
-                        is_capture,
-                        promoted_to,
-                    )?;
-                }
-                ref c @ CastleKingside | ref c @ CastleQueenside => {
-                    let rank = if game_move.number.is_some() {
-                        Rank::R8
-                    } else {
-                        Rank::R1
+                    let known_from = match from.get_known() {
+                        Some(known_from) => known_from,
+                        None => self.disambiguate_from_square(piece.into(), color, from, to)?,
                     };
-                    let (old_rook_file, new_king_file, new_rook_file) = match c {
-                        CastleKingside => (File::H, File::G, File::F),
-                        CastleQueenside => (File::A, File::C, File::D),
-                        _ => unreachable!(),
-                    };
-                    self.add_castle_move(rank, old_rook_file, new_king_file, new_rook_file)?;
+                    self.simple_move(&known_from, to, promoted_to.map(PieceKind::from))?;
                 }
+                CastleKingside => self.castle(true, color)?,
+                CastleQueenside => self.castle(false, color)?,
             }
         }
         Ok(())
@@ -282,67 +838,189 @@ impl Board {
         piece_data.behavior.get_valid_squares(piece_data, self)
     }
 
-    pub fn get_valid_squares_for_piece(&self, piece: UniquePiece, white: bool) -> Vec<Square> {
+    pub fn get_valid_squares_for_piece(&self, piece: UniquePiece, color: Color) -> Vec<Square> {
         let piece_data = self
             .pieces
             .iter()
-            .find(|p| p.piece == piece && p.white == white)
+            .find(|p| p.piece == piece && p.color == color)
             .expect("missing piece");
         if piece_data.curr_square().is_some() {
-            piece_data.behavior.get_valid_squares(piece_data, &self)
+            piece_data.behavior.get_valid_squares(piece_data, self)
         } else {
             Vec::new()
         }
     }
 
     pub fn get_piece_data_at_square(&self, square: &Square) -> Option<&PieceData> {
-        for piece in self.pieces.iter() {
-            if let Some(curr_square) = piece.curr_square() {
-                if curr_square == square {
-                    return Some(piece);
-                }
-            }
-        }
-        return None;
+        let index = self.bitboards().at(bitboard::square_index(square)?)?;
+        Some(&self.pieces[index])
     }
 
+    // Moving the piece through the returned reference is not supported, use `simple_move`
     pub fn get_mut_piece_data_at_square(&mut self, square: &Square) -> Option<&mut PieceData> {
-        for piece in self.pieces.iter_mut() {
-            if let Some(curr_square) = piece.curr_square() {
-                if curr_square == square {
-                    return Some(piece);
-                }
-            }
+        let index = self.bitboards().at(bitboard::square_index(square)?)?;
+        self.stale_bitboards.set(true);
+        Some(&mut self.pieces[index])
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        match self.get_king_square(color) {
+            Some(king_square) => self.is_square_attacked(king_square, !color),
+            None => false,
         }
-        return None;
     }
 
-    fn get_all_live_piece_data_with_type(&self, piece: Piece, white: bool) -> Vec<&PieceData> {
+    // Attack map: every square threatened by the pieces of the given color
+    pub fn attacked_squares(&self, by_color: Color) -> HashSet<Square> {
         self.pieces
             .iter()
-            .filter(|p| {
-                piece == Self::unique_to_piece(p.piece)
-                    && p.curr_square().is_some()
-                    && p.white == white
-            })
+            .filter(|p| p.color == by_color && p.curr_square().is_some())
+            .flat_map(|p| p.behavior.get_attacked_squares(p, self))
             .collect()
     }
 
-    fn unique_to_piece(unique_piece: UniquePiece) -> Piece {
-        match unique_piece {
-            UniquePiece::QRook | UniquePiece::KRook => Piece::Rook,
-            UniquePiece::QKnight | UniquePiece::KKnight => Piece::Knight,
-            UniquePiece::QBishop | UniquePiece::KBishop => Piece::Bishop,
-            UniquePiece::Queen => Piece::Queen,
-            UniquePiece::King => Piece::King,
-            _ => Piece::Pawn,
+    fn is_square_attacked(&self, square: &Square, by_color: Color) -> bool {
+        match bitboard::square_index(square) {
+            Some(index) => {
+                let bitboards = self.bitboards();
+                bitboards.attackers(index, by_color, bitboards.occupied(), 0) != 0
+            }
+            None => false,
+        }
+    }
+
+    fn get_king_square(&self, color: Color) -> Option<&Square> {
+        let bitboards = self.bitboards();
+        let kings = bitboards.pieces(PieceKind::King, color);
+        if kings == 0 {
+            return None;
+        }
+        let index = bitboards.at(kings.trailing_zeros() as usize)?;
+        drop(bitboards);
+        self.pieces[index].curr_square()
+    }
+
+    // If the piece is pinned to its own king, returns the squares it may still move to
+    // (the line between the king and the pinning piece, including the pinner).
+    fn get_pin_ray(&self, piece_data: &PieceData) -> Option<Vec<Square>> {
+        let king_square = self.get_king_square(piece_data.color)?;
+        let square = piece_data.curr_square()?;
+        let (king_file, king_rank) = square_index::square_coords(king_square)?;
+        let (file, rank) = square_index::square_coords(square)?;
+        let (file_diff, rank_diff) = (file - king_file, rank - king_rank);
+        let diagonal = file_diff.abs() == rank_diff.abs();
+        if (file_diff, rank_diff) == (0, 0) || !(diagonal || file_diff == 0 || rank_diff == 0) {
+            return None;
+        }
+
+        let (file_offset, rank_offset) = (file_diff.signum(), rank_diff.signum());
+        let mut ray = Vec::new();
+        let mut curr_square = king_square.new_with_offset(file_offset, rank_offset)?;
+        while curr_square != *square {
+            if self.get_piece_data_at_square(&curr_square).is_some() {
+                return None;
+            }
+            ray.push(curr_square.clone());
+            curr_square = curr_square.new_with_offset(file_offset, rank_offset)?;
+        }
+
+        loop {
+            curr_square = curr_square.new_with_offset(file_offset, rank_offset)?;
+            ray.push(curr_square.clone());
+            if let Some(other) = self.get_piece_data_at_square(&curr_square) {
+                let pins = other.color != piece_data.color
+                    && match other.behavior.kind() {
+                        PieceKind::Queen => true,
+                        PieceKind::Bishop => diagonal,
+                        PieceKind::Rook => !diagonal,
+                        _ => false,
+                    };
+                return if pins { Some(ray) } else { None };
+            }
         }
     }
+
+    // Reports whether the mover's king would be attacked after the move, by looking at the
+    // occupancy the move leaves behind
+    fn leaves_king_in_check(&self, piece_data: &PieceData, to: &Square) -> bool {
+        let from = match piece_data.curr_square() {
+            Some(from) => from,
+            None => return false,
+        };
+        let captured_square = if self.is_en_passant(piece_data, to) {
+            match (to.file(), from.rank()) {
+                (Some(file), Some(rank)) => Square::new_known(file, rank),
+                _ => return false,
+            }
+        } else {
+            to.clone()
+        };
+        let (from_index, to_index, captured_index) = match (
+            bitboard::square_index(from),
+            bitboard::square_index(to),
+            bitboard::square_index(&captured_square),
+        ) {
+            (Some(from_index), Some(to_index), Some(captured_index)) => {
+                (from_index, to_index, captured_index)
+            }
+            _ => return false,
+        };
+        let king_index = if piece_data.behavior.kind() == PieceKind::King {
+            to_index
+        } else {
+            match self
+                .get_king_square(piece_data.color)
+                .and_then(bitboard::square_index)
+            {
+                Some(king_index) => king_index,
+                None => return false,
+            }
+        };
+        let captured: u64 = 1 << captured_index;
+        let bitboards = self.bitboards();
+        let occupied = (bitboards.occupied() & !(1 << from_index) & !captured) | 1 << to_index;
+        bitboards.attackers(king_index, !piece_data.color, occupied, captured) != 0
+    }
+
+    pub(crate) fn filter_legal_squares(
+        &self,
+        piece_data: &PieceData,
+        squares: Vec<Square>,
+    ) -> Vec<Square> {
+        let is_king = piece_data.piece == UniquePiece::King;
+        let in_check = self.is_in_check(piece_data.color);
+        let pin_ray = self.get_pin_ray(piece_data);
+        squares
+            .into_iter()
+            .filter(|to| {
+                if let Some(pin_ray) = &pin_ray {
+                    if !pin_ray.contains(to) {
+                        return false;
+                    }
+                }
+                // Castling squares were already checked for attacks
+                if is_king && self.get_castling_side(piece_data, to).is_some() {
+                    return true;
+                }
+                if is_king || in_check || self.is_en_passant(piece_data, to) {
+                    return !self.leaves_king_in_check(piece_data, to);
+                }
+                true
+            })
+            .collect()
+    }
+
+    fn get_all_live_piece_data_with_type(&self, piece: PieceKind, color: Color) -> Vec<&PieceData> {
+        self.pieces
+            .iter()
+            .filter(|p| piece == p.behavior.kind() && p.curr_square().is_some() && p.color == color)
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{ChessError, UniquePiece};
+    use crate::{ChessError, Color, Outcome, PieceKind, UniquePiece};
 
     use super::Board;
     use chess_pgn_parser::Square;
@@ -362,21 +1040,57 @@ mod tests {
             .get_piece_data_at_square(&Square::D4)
             .expect("missing piece.");
         assert_eq!(pawn.piece, UniquePiece::DPawn);
-        let valid_squares = pawn.behavior.get_valid_squares(&pawn, &board);
+        let valid_squares = pawn.behavior.get_valid_squares(pawn, &board);
         assert_valid_squares(&[Square::E5, Square::D5], &valid_squares);
 
         let pawn = board
             .get_piece_data_at_square(&Square::E5)
             .expect("missing piece.");
         assert_eq!(pawn.piece, UniquePiece::EPawn);
-        let valid_squares = pawn.behavior.get_valid_squares(&pawn, &board);
+        let valid_squares = pawn.behavior.get_valid_squares(pawn, &board);
         assert_valid_squares(&[Square::D4, Square::E4], &valid_squares);
         Ok(())
     }
 
     #[test]
     fn test_pawn_en_passant() -> Result<(), ChessError> {
-        // TODO
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 a6 2. e5 d5")?;
+        assert_eq!(board.en_passant_square(), Some(&Square::D6));
+        assert_valid_squares(
+            &[Square::E6, Square::D6],
+            &board.legal_moves_from_square(&Square::E5),
+        );
+
+        board.add_pgn_moves("3. exd6")?;
+        assert!(board.en_passant_square().is_none());
+        assert!(board.get_piece_data_at_square(&Square::D5).is_none());
+        let pawn = board
+            .get_piece_data_at_square(&Square::D6)
+            .expect("missing piece.");
+        assert_eq!(pawn.piece, UniquePiece::EPawn);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pawn_en_passant_simple_move() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. a3 d5 2. a4 d4 3. e4")?;
+        assert_valid_squares(
+            &[Square::D3, Square::E3],
+            &board.legal_moves_from_square(&Square::D4),
+        );
+        board.simple_move(&Square::D4, &Square::E3, None)?;
+        assert!(board.get_piece_data_at_square(&Square::E4).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pawn_en_passant_expires() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 a6 2. e5 d5 3. a3 a5")?;
+        assert!(board.en_passant_square().is_none());
+        assert_valid_squares(&[Square::E6], &board.legal_moves_from_square(&Square::E5));
         Ok(())
     }
 
@@ -561,10 +1275,462 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_check_evasion() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5 2. d4 Bb4")?;
+        assert!(board.is_in_check(Color::White));
+        assert_valid_squares(
+            &[Square::C3, Square::D2],
+            &board.legal_moves_from_square(&Square::B1),
+        );
+        assert_valid_squares(&[Square::E2], &board.legal_moves_from_square(&Square::E1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_piece() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. d4 e6 2. c3 Bb4")?;
+        assert!(!board.is_in_check(Color::White));
+        assert_valid_squares(&[Square::B4], &board.legal_moves_from_square(&Square::C3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_king_cannot_move_into_check() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 f5 2. Qh5")?;
+        assert!(board.is_in_check(Color::Black));
+        assert!(board.legal_moves_from_square(&Square::E8).is_empty());
+        assert_valid_squares(&[Square::G6], &board.legal_moves_from_square(&Square::G7));
+        assert!(board.attacked_squares(Color::White).contains(&Square::F7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_castling() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5")?;
+        assert_valid_squares(
+            &[Square::E2, Square::F1, Square::G1],
+            &board.legal_moves_from_square(&Square::E1),
+        );
+        board.simple_move(&Square::E1, &Square::G1, None)?;
+        let king = board
+            .get_piece_data_at_square(&Square::G1)
+            .expect("missing piece.");
+        assert_eq!(king.piece, UniquePiece::King);
+        let rook = board
+            .get_piece_data_at_square(&Square::F1)
+            .expect("missing piece.");
+        assert_eq!(rook.piece, UniquePiece::KRook);
+        assert!(!board.castling_rights().white_king_side);
+        assert!(!board.castling_rights().white_queen_side);
+        assert!(board.castling_rights().black_king_side);
+        Ok(())
+    }
+
+    #[test]
+    fn test_king_hop_is_not_castling() -> Result<(), ChessError> {
+        for (fen, from, to) in [
+            ("4k3/8/8/8/4K3/8/8/7R w - - 0 1", Square::E4, Square::G4),
+            ("4k3/8/8/8/8/8/8/4K2R w - - 0 1", Square::E1, Square::G1),
+        ] {
+            let mut board = Board::from_fen(fen)?;
+            match board.simple_move(&from, &to, None) {
+                Err(ChessError::IlegalMove(
+                    PieceKind::King,
+                    Color::White,
+                    error_from,
+                    error_to,
+                )) => {
+                    assert_eq!((error_from, error_to), (from, to));
+                }
+                result => panic!("unexpected result {:?}", result),
+            }
+            assert_eq!(board.to_fen(), fen);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_castling_through_attacked_square() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. Nh3 e6 2. g3 Bc5 3. Bg2 a6 4. f4 a5")?;
+        assert_valid_squares(&[Square::F1], &board.legal_moves_from_square(&Square::E1));
+        assert!(board.castle(true, Color::White).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_castling_rights_lost() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. Rg1 a6 5. Rh1 a5")?;
+        assert!(!board.castling_rights().white_king_side);
+        assert!(board.castling_rights().white_queen_side);
+        assert_valid_squares(
+            &[Square::E2, Square::F1],
+            &board.legal_moves_from_square(&Square::E1),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_side_to_move() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        assert_eq!(board.side_to_move(), Color::White);
+        assert!(matches!(
+            board.simple_move(&Square::E7, &Square::E5, None),
+            Err(ChessError::OutOfTurn(PieceKind::Pawn, Color::Black))
+        ));
+        board.simple_move(&Square::E2, &Square::E4, None)?;
+        assert_eq!(board.side_to_move(), Color::Black);
+        assert!(matches!(
+            board.castle(true, Color::White),
+            Err(ChessError::OutOfTurn(PieceKind::King, Color::White))
+        ));
+        board.simple_move(&Square::E7, &Square::E5, None)?;
+        assert_eq!(board.side_to_move(), Color::White);
+        Ok(())
+    }
+
+    #[test]
+    fn test_simple_move_rejects_illegal_moves() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        assert!(matches!(
+            board.simple_move(&Square::E4, &Square::E5, None),
+            Err(ChessError::EmptySquare(_))
+        ));
+        assert!(matches!(
+            board.simple_move(&Square::E2, &Square::E5, None),
+            Err(ChessError::IlegalMove(PieceKind::Pawn, Color::White, _, _))
+        ));
+        assert!(matches!(
+            board.simple_move(&Square::E2, &Square::E4, Some(PieceKind::Queen)),
+            Err(ChessError::InvalidPromotion(_, Some(PieceKind::Queen)))
+        ));
+
+        board.add_pgn_moves("1. d4 e6 2. c3 Bb4")?;
+        assert!(matches!(
+            board.simple_move(&Square::C3, &Square::C4, None),
+            Err(ChessError::IlegalMove(PieceKind::Pawn, Color::White, _, _))
+        ));
+        assert_eq!(board.side_to_move(), Color::White);
+        assert!(board.get_piece_data_at_square(&Square::C3).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkmate() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. f3 e5 2. g4")?;
+        assert_eq!(board.outcome(), None);
+        board.add_pgn_moves("2... Qh4")?;
+        assert_eq!(
+            board.outcome(),
+            Some(Outcome::Checkmate {
+                winner: Color::Black
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stalemate() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves(
+            "1. e3 a5 2. Qh5 Ra6 3. Qxa5 h5 4. h4 Rah6 5. Qxc7 f6 6. Qxd7 Kf7 \
+             7. Qxb7 Qd3 8. Qxb8 Qh7 9. Qxc8 Kg6 10. Qe6",
+        )?;
+        assert_eq!(board.outcome(), Some(Outcome::Stalemate));
+        Ok(())
+    }
+
+    #[test]
+    fn test_repetition() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1")?;
+        assert_eq!(board.repetition_count(), 2);
+        assert_eq!(board.outcome(), None);
+        board.add_pgn_moves("4... Ng8")?;
+        assert_eq!(board.outcome(), Some(Outcome::ThreefoldRepetition));
+        board.add_pgn_moves("5. Nf3 Nf6 6. Ng1 Ng8 7. Nf3 Nf6 8. Ng1 Ng8")?;
+        assert_eq!(board.repetition_count(), 5);
+        assert_eq!(board.outcome(), Some(Outcome::FivefoldRepetition));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_clock_draws() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. Nf3 Nf6")?;
+        assert_eq!(board.halfmove_clock(), 2);
+        board.add_pgn_moves("2. e4")?;
+        assert_eq!(board.halfmove_clock(), 0);
+
+        board.halfmove_clock = 100;
+        assert_eq!(board.outcome(), Some(Outcome::FiftyMoveRule));
+        board.halfmove_clock = 150;
+        assert_eq!(board.outcome(), Some(Outcome::SeventyFiveMoveRule));
+        Ok(())
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        let mut board = Board::new();
+        let squares: Vec<Square> = board
+            .pieces
+            .iter()
+            .filter(|p| {
+                p.piece != UniquePiece::King
+                    && p.piece != UniquePiece::KBishop
+                    && p.piece != UniquePiece::QBishop
+            })
+            .map(|p| p.curr_square().unwrap().clone())
+            .collect();
+        for square in squares {
+            board
+                .get_mut_piece_data_at_square(&square)
+                .expect("missing piece")
+                .capture();
+        }
+        // Both sides keep two bishops on opposite colors
+        assert_eq!(board.outcome(), None);
+
+        board
+            .get_mut_piece_data_at_square(&Square::C1)
+            .expect("missing piece")
+            .capture();
+        board
+            .get_mut_piece_data_at_square(&Square::F8)
+            .expect("missing piece")
+            .capture();
+        // Remaining bishops on f1 and c8 are both on light squares
+        assert_eq!(board.outcome(), Some(Outcome::InsufficientMaterial));
+    }
+
+    #[test]
+    fn test_pgn_turn_order() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5 2. Nf3 Nc6 3. Bc4 3... Bc5 4. O-O Nf6 5. d3 O-O")?;
+        let king = board
+            .get_piece_data_at_square(&Square::G1)
+            .expect("missing piece.");
+        assert_eq!(king.piece, UniquePiece::King);
+        assert_eq!(king.color, Color::White);
+        let king = board
+            .get_piece_data_at_square(&Square::G8)
+            .expect("missing piece.");
+        assert_eq!(king.piece, UniquePiece::King);
+        assert_eq!(king.color, Color::Black);
+        assert_eq!(
+            board.get_piece_data_at_square(&Square::C5).unwrap().color,
+            Color::Black
+        );
+        assert_eq!(board.side_to_move(), Color::White);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_from_black_to_move() -> Result<(), ChessError> {
+        let mut board =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1")?;
+        board.add_pgn_moves("1... e5 2. Nf3 Nc6")?;
+        assert_eq!(
+            board.get_piece_data_at_square(&Square::E5).unwrap().color,
+            Color::Black
+        );
+        assert_eq!(
+            board.get_piece_data_at_square(&Square::F3).unwrap().color,
+            Color::White
+        );
+        assert_eq!(
+            board.get_piece_data_at_square(&Square::C6).unwrap().color,
+            Color::Black
+        );
+        assert_eq!(board.fullmove_number(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_validates_moves() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        assert!(matches!(
+            board.add_pgn_moves("1. e4 e5 2. Ke3"),
+            Err(ChessError::UnreachableSquare(
+                PieceKind::King,
+                Color::White,
+                _
+            ))
+        ));
+
+        let mut board = Board::new();
+        board.add_pgn_moves("1. Nf3 d5 2. d3 e5")?;
+        assert!(matches!(
+            board.add_pgn_moves("3. Nd2"),
+            Err(ChessError::UnDisambiguable)
+        ));
+        board.add_pgn_moves("3. Nbd2")?;
+        assert_eq!(
+            board.get_piece_data_at_square(&Square::D2).unwrap().piece,
+            UniquePiece::QKnight
+        );
+
+        let mut board = Board::new();
+        assert!(matches!(
+            board.add_pgn_moves("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. O-O-O"),
+            Err(ChessError::IlegalMove(PieceKind::King, Color::White, _, _))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_list() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        assert_eq!(board.position_count(), 1);
+        board.add_pgn_moves("1. e4 d5 2. exd5 Nf6 3. Nf3 Nxd5 4. Bc4 e5 5. O-O")?;
+        assert_eq!(board.position_count(), 10);
+
+        let records = board.move_list();
+        assert_eq!(records.len(), 9);
+        assert_eq!(records[0].piece, UniquePiece::EPawn);
+        assert_eq!(records[0].color, Color::White);
+        assert_eq!(records[0].prev_en_passant_square, None);
+        assert_eq!(records[1].prev_en_passant_square, Some(Square::E3));
+        assert_eq!(records[2].captured_piece, Some(UniquePiece::DPawn));
+        assert_eq!(records[2].captured_square(), Some(Square::D5));
+        assert_eq!(records[5].captured_piece, Some(UniquePiece::EPawn));
+        assert_eq!(records[5].piece, UniquePiece::KKnight);
+        assert_eq!(records[5].prev_halfmove_clock, 2);
+        assert_eq!(records[5].prev_fullmove_number, 3);
+
+        let castle = records.last().unwrap();
+        assert!(castle.castling);
+        assert_eq!(castle.from, Square::E1);
+        assert_eq!(castle.to, Square::G1);
+        assert!(castle.prev_castling_rights.white_king_side);
+        assert!(!board.castling_rights().white_king_side);
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_list_en_passant() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 a6 2. e5 d5 3. exd6")?;
+        let record = board.move_list().last().unwrap();
+        assert!(record.en_passant);
+        assert_eq!(record.captured_piece, Some(UniquePiece::DPawn));
+        assert_eq!(record.captured_square(), Some(Square::D5));
+        assert_eq!(record.prev_en_passant_square, Some(Square::D6));
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_redo() -> Result<(), ChessError> {
+        let moves = "1. e4 d5 2. exd5 Nf6 3. Nf3 Nxd5 4. Bc4 e5 5. O-O e4 6. d4 exd3 \
+                     7. Be3 dxc2 8. Bd2 cxb1=Q";
+        let mut board = Board::new();
+        let mut fens = vec![board.to_fen()];
+        let mut replay = Board::new();
+        for san in moves
+            .split_whitespace()
+            .filter(|token| !token.ends_with('.'))
+        {
+            replay.add_pgn_moves(&format!(
+                "{}{} {}",
+                replay.fullmove_number(),
+                if replay.side_to_move().is_white() {
+                    "."
+                } else {
+                    "..."
+                },
+                san
+            ))?;
+            fens.push(replay.to_fen());
+        }
+        board.add_pgn_moves(moves)?;
+        assert_eq!(board.to_fen(), *fens.last().unwrap());
+
+        for fen in fens.iter().rev().skip(1) {
+            assert!(board.undo().is_some());
+            assert_eq!(board.to_fen(), *fen);
+        }
+        assert!(board.undo().is_none());
+        assert_eq!(board.repetition_count(), 1);
+
+        for fen in fens.iter().skip(1) {
+            assert!(board.redo().is_some());
+            assert_eq!(board.to_fen(), *fen);
+        }
+        assert!(board.redo().is_none());
+        assert_eq!(board.san_moves()?.last().unwrap(), "cxb1=Q");
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_undo_redo_change_nothing() -> Result<(), ChessError> {
+        // The knight that moved is taken off behind the board's back, so it can't move back
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5 2. Nf3")?;
+        board
+            .get_mut_piece_data_at_square(&Square::F3)
+            .expect("missing piece")
+            .capture();
+        let (fen, hash) = (board.to_fen(), board.hash());
+        let moves = board.move_list().to_vec();
+        assert!(board.undo().is_none());
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(board.hash(), hash);
+        assert_eq!(board.move_list(), &moves[..]);
+
+        // The pawn to play again is gone, and the move stays there to redo
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5")?;
+        board.undo();
+        board.undo();
+        board
+            .get_mut_piece_data_at_square(&Square::E2)
+            .expect("missing piece")
+            .capture();
+        let (fen, hash) = (board.to_fen(), board.hash());
+        assert!(board.redo().is_none());
+        assert_eq!(board.to_fen(), fen);
+        assert_eq!(board.hash(), hash);
+        assert!(board.move_list().is_empty());
+        assert_eq!(board.redo_list.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_move_clears_redo() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5")?;
+        let record = board.undo().expect("missing move.");
+        assert_eq!(record.from, Square::E7);
+        board.simple_move(&Square::C7, &Square::C5, None)?;
+        assert!(board.redo().is_none());
+        assert_eq!(board.san_moves()?, vec!["e4", "c5"]);
+
+        // The promoted pawn is a pawn again once the promotion is taken back
+        let mut board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1")?;
+        board.simple_move(&Square::B7, &Square::B8, Some(PieceKind::Queen))?;
+        board.undo();
+        assert_eq!(
+            board
+                .get_piece_data_at_square(&Square::B7)
+                .expect("missing piece.")
+                .behavior
+                .kind(),
+            PieceKind::Pawn
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_promotion() -> Result<(), ChessError> {
         let mut board = Board::new();
-        board.add_pgn_moves("1. c4 f5 2. c5 f4 3. c6 f3 4. cxb7 fxg2 5. bxc8=N gxf8=N")?;
+        board.add_pgn_moves("1. c4 f5 2. c5 f4 3. c6 f3 4. cxb7 fxg2 5. bxc8=N gxf1=N")?;
         let promoted_knight = board
             .get_piece_data_at_square(&Square::C8)
             .expect("missing piece.");
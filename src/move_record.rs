@@ -0,0 +1,34 @@
+use chess_pgn_parser::Square;
+
+use crate::{CastlingRights, Color, PieceKind, UniquePiece};
+
+// A move as it was played, with the state it replaced so that it can be taken back
+#[derive(Debug, PartialEq, Clone)]
+pub struct MoveRecord {
+    pub from: Square,
+    pub to: Square,
+    pub piece: UniquePiece,
+    pub color: Color,
+    pub captured_piece: Option<UniquePiece>,
+    pub en_passant: bool,
+    pub promoted_to: Option<PieceKind>,
+    // For castling `from` is the king's square and `to` its destination, or on Chess960
+    // boards the square of the rook it castles with
+    pub castling: bool,
+    pub prev_castling_rights: CastlingRights,
+    pub prev_en_passant_square: Option<Square>,
+    pub prev_halfmove_clock: u32,
+    pub prev_fullmove_number: u32,
+}
+
+impl MoveRecord {
+    // Where the captured piece stood, which differs from `to` for en passant
+    pub fn captured_square(&self) -> Option<Square> {
+        self.captured_piece?;
+        if self.en_passant {
+            Some(Square::new_known(self.to.file()?, self.from.rank()?))
+        } else {
+            Some(self.to.clone())
+        }
+    }
+}
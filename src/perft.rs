@@ -0,0 +1,141 @@
+use crate::Board;
+
+impl Board {
+    // Number of leaf nodes of the legal move tree `depth` plies deep, the standard check
+    // of move generation against published counts
+    pub fn perft(&self, depth: u32) -> u64 {
+        self.clone().perft_in_place(depth)
+    }
+
+    // Perft counts split by first move, in UCI notation like "e2e4" or "b7b8q", to narrow a
+    // miscount down to the move that causes it
+    pub fn perft_divide(&self, depth: u32) -> Vec<(String, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        let mut board = self.clone();
+        let mut divide = Vec::new();
+        for m in board.legal_moves() {
+            board
+                .play_legal_move(&m)
+                .expect("generated moves are legal");
+            divide.push((m.to_string(), board.perft_in_place(depth - 1)));
+            board.undo();
+        }
+        divide
+    }
+
+    fn perft_in_place(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for m in moves {
+            self.play_legal_move(&m).expect("generated moves are legal");
+            nodes += self.perft_in_place(depth - 1);
+            self.undo();
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, ChessError, STARTING_FEN};
+
+    // Published perft counts from https://www.chessprogramming.org/Perft_Results
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    // Chess960 positions from the perft suite at https://www.chessprogramming.org/Chess960_Perft_Results
+    const CHESS960_1: &str = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+    const CHESS960_2: &str = "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9";
+    const CHESS960_3: &str = "b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9";
+
+    fn assert_perft(fen: &str, expected: &[u64]) -> Result<(), ChessError> {
+        let board = Board::from_fen(fen)?;
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(
+                board.perft(depth as u32 + 1),
+                *nodes,
+                "{} at depth {}",
+                fen,
+                depth + 1
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_perft_initial_position() -> Result<(), ChessError> {
+        assert_perft(STARTING_FEN, &[20, 400, 8_902])
+    }
+
+    #[test]
+    fn test_perft_kiwipete() -> Result<(), ChessError> {
+        assert_perft(KIWIPETE, &[48, 2_039])
+    }
+
+    #[test]
+    fn test_perft_position_3() -> Result<(), ChessError> {
+        assert_perft(POSITION_3, &[14, 191, 2_812])
+    }
+
+    #[test]
+    fn test_perft_position_4() -> Result<(), ChessError> {
+        assert_perft(POSITION_4, &[6, 264, 9_467])
+    }
+
+    #[test]
+    fn test_perft_position_5() -> Result<(), ChessError> {
+        assert_perft(POSITION_5, &[44, 1_486])
+    }
+
+    #[test]
+    fn test_perft_position_6() -> Result<(), ChessError> {
+        assert_perft(POSITION_6, &[46, 2_079])
+    }
+
+    #[test]
+    fn test_perft_chess960() -> Result<(), ChessError> {
+        assert_perft(CHESS960_1, &[21, 528, 12_189])?;
+        assert_perft(CHESS960_2, &[21, 807, 18_002])?;
+        assert_perft(CHESS960_3, &[20, 479, 10_471])
+    }
+
+    // Deeper counts take a while in debug builds: cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn test_perft_deep() -> Result<(), ChessError> {
+        assert_perft(STARTING_FEN, &[20, 400, 8_902, 197_281])?;
+        assert_perft(KIWIPETE, &[48, 2_039, 97_862])?;
+        assert_perft(POSITION_3, &[14, 191, 2_812, 43_238, 674_624])?;
+        assert_perft(POSITION_4, &[6, 264, 9_467, 422_333])?;
+        assert_perft(POSITION_5, &[44, 1_486, 62_379])?;
+        assert_perft(POSITION_6, &[46, 2_079, 89_890])?;
+        assert_perft(CHESS960_1, &[21, 528, 12_189, 326_672])?;
+        assert_perft(CHESS960_2, &[21, 807, 18_002, 667_366])?;
+        assert_perft(CHESS960_3, &[20, 479, 10_471, 273_318])
+    }
+
+    #[test]
+    fn test_perft_divide() -> Result<(), ChessError> {
+        let board = Board::from_fen(POSITION_5)?;
+        let divide = board.perft_divide(2);
+        assert_eq!(divide.len(), 44);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 1_486);
+        assert!(divide.iter().any(|(name, _)| name == "d7c8q"));
+        assert!(divide.iter().any(|(name, _)| name == "e1g1"));
+        assert_eq!(board.perft(0), 1);
+        assert!(board.perft_divide(0).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{Board, ChessError, Game, GameTree};
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// Variant tag values naming Chess960, as written by common chess software
+const CHESS960_VARIANTS: [&str; 4] = ["chess960", "chess 960", "fischerandom", "fischer random"];
+
+type TagPairs = Vec<(String, String)>;
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Nag(u8),
+    Move(String),
+    Result(String),
+}
+
+// A line being read: the board before its next move, the board before its last move (where
+// variations branch off) and the tree path of the last move
+struct Line {
+    board: Board,
+    prev_board: Option<Board>,
+    path: Vec<usize>,
+    comments: Vec<String>,
+}
+
+impl Game {
+    // Reads a PGN game keeping its comments, variations and annotation glyphs, so that
+    // `to_pgn` writes it back unchanged
+    pub fn from_pgn(pgn: &str) -> Result<Game, ChessError> {
+        Self::read_pgn(pgn, &mut None)
+    }
+
+    // Like `from_pgn`, also telling which move failed, if one did, by its ply in its line
+    // and its SAN text
+    pub(crate) fn read_pgn(
+        pgn: &str,
+        failed_move: &mut Option<(usize, String)>,
+    ) -> Result<Game, ChessError> {
+        let (tags, movetext) = parse_tags(pgn)?;
+        let mut board = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Board::from_fen(fen)?,
+            None => Board::new(),
+        };
+        if tags.iter().any(|(name, value)| {
+            name == "Variant" && CHESS960_VARIANTS.contains(&value.to_lowercase().as_str())
+        }) {
+            board.chess960 = true;
+        }
+        let mut tree = GameTree::from_board(board.clone());
+        let mut lines = vec![Line {
+            board,
+            prev_board: None,
+            path: Vec::new(),
+            comments: Vec::new(),
+        }];
+        let mut result = None;
+        for token in tokenize(movetext)? {
+            if result.is_some() {
+                return Err(ChessError::PgnParseError);
+            }
+            let line = lines.last_mut().unwrap();
+            match token {
+                Token::Comment(comment) => match (&line.prev_board, tree.node_mut(&line.path)) {
+                    (Some(_), Some(node)) => node.comments.push(comment),
+                    _ => line.comments.push(comment),
+                },
+                Token::Nag(nag) => match (&line.prev_board, tree.node_mut(&line.path)) {
+                    (Some(_), Some(node)) => node.nags.push(nag),
+                    _ => return Err(ChessError::PgnParseError),
+                },
+                Token::Move(text) => {
+                    *failed_move = Some((line.path.len() + 1, text.clone()));
+                    let m = line.board.from_san(&text)?;
+                    let prev_board = line.board.clone();
+                    line.board.apply_move(&m)?;
+                    *failed_move = None;
+                    let record = line.board.move_list().last().unwrap().clone();
+                    line.path = tree.add_record(&line.path, record);
+                    line.prev_board = Some(prev_board);
+                    let node = tree.node_mut(&line.path).unwrap();
+                    node.comments_before.append(&mut line.comments);
+                    let suffix = text.trim_start_matches(|c| !"!?".contains(c));
+                    if !suffix.is_empty() {
+                        node.suffix = Some(suffix.to_string());
+                    }
+                }
+                Token::VariationStart => {
+                    let board = line.prev_board.clone().ok_or(ChessError::PgnParseError)?;
+                    let path = line.path[..line.path.len() - 1].to_vec();
+                    lines.push(Line {
+                        board,
+                        prev_board: None,
+                        path,
+                        comments: Vec::new(),
+                    });
+                }
+                Token::VariationEnd => {
+                    if lines.len() == 1 {
+                        return Err(ChessError::PgnParseError);
+                    }
+                    lines.pop();
+                }
+                Token::Result(token) => {
+                    if lines.len() > 1 {
+                        return Err(ChessError::PgnParseError);
+                    }
+                    result = Some(token);
+                }
+            }
+        }
+        if lines.len() > 1 {
+            return Err(ChessError::PgnParseError);
+        }
+        tree.comments.append(&mut lines[0].comments);
+
+        let mut game = Game::from_tree(tree);
+        for (name, value) in tags.iter() {
+            game.set_tag(name, value);
+        }
+        if let Some(result) = result {
+            game.set_tag("Result", &result);
+        }
+        Ok(game)
+    }
+}
+
+// Splits a game into its tag pairs and its movetext
+fn parse_tags(pgn: &str) -> Result<(TagPairs, &str), ChessError> {
+    let mut tags = Vec::new();
+    let mut rest = pgn.trim_start();
+    while let Some(tag) = rest.strip_prefix('[') {
+        let (name, tag) = tag
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .ok_or(ChessError::PgnParseError)?;
+        let mut chars = tag.trim_start().char_indices();
+        if chars.next().map(|(_, c)| c) != Some('"') {
+            return Err(ChessError::PgnParseError);
+        }
+        let mut value = String::new();
+        let mut end = None;
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '\\' => value.push(chars.next().ok_or(ChessError::PgnParseError)?.1),
+                '"' => {
+                    end = Some(index + 1);
+                    break;
+                }
+                _ => value.push(c),
+            }
+        }
+        let tag = &tag.trim_start()[end.ok_or(ChessError::PgnParseError)?..];
+        rest = tag
+            .trim_start()
+            .strip_prefix(']')
+            .ok_or(ChessError::PgnParseError)?
+            .trim_start();
+        tags.push((name.to_string(), value));
+    }
+    Ok((tags, rest))
+}
+
+fn tokenize(movetext: &str) -> Result<Vec<Token>, ChessError> {
+    let mut tokens = Vec::new();
+    let mut chars = movetext.char_indices().peekable();
+    let mut line_start = true;
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\n' => line_start = true,
+            _ if c.is_whitespace() => {}
+            // Escaped lines are meant for other programs
+            '%' if line_start => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '{' => {
+                let len = movetext[start + 1..]
+                    .find('}')
+                    .ok_or(ChessError::PgnParseError)?;
+                tokens.push(Token::Comment(
+                    movetext[start + 1..start + 1 + len].to_string(),
+                ));
+                while chars
+                    .next_if(|(index, _)| *index <= start + 1 + len)
+                    .is_some()
+                {}
+            }
+            ';' => {
+                let len = movetext[start + 1..]
+                    .find('\n')
+                    .unwrap_or(movetext.len() - start - 1);
+                tokens.push(Token::Comment(
+                    movetext[start + 1..start + 1 + len].trim().to_string(),
+                ));
+                while chars
+                    .next_if(|(index, _)| *index < start + 1 + len)
+                    .is_some()
+                {}
+            }
+            '(' => tokens.push(Token::VariationStart),
+            ')' => tokens.push(Token::VariationEnd),
+            _ => {
+                let mut end = movetext.len();
+                while let Some((index, c)) = chars.peek() {
+                    if c.is_whitespace() || "{}();".contains(*c) {
+                        end = *index;
+                        break;
+                    }
+                    chars.next();
+                }
+                let word = &movetext[start..end];
+                if let Some(nag) = word.strip_prefix('$') {
+                    tokens.push(Token::Nag(
+                        nag.parse().map_err(|_| ChessError::PgnParseError)?,
+                    ));
+                } else if RESULTS.contains(&word) {
+                    tokens.push(Token::Result(word.to_string()));
+                } else {
+                    let text = strip_move_number(word);
+                    // "e.p." may follow an en passant capture as a word of its own
+                    if !text.is_empty() && text != "e.p." {
+                        tokens.push(Token::Move(text.to_string()));
+                    }
+                }
+            }
+        }
+        if c != '\n' {
+            line_start = false;
+        }
+    }
+    Ok(tokens)
+}
+
+// Writes castling with zeros, like "0-0", with letters instead, for the PGN parser
+pub(crate) fn letter_castling(movetext: &str) -> String {
+    movetext
+        .split_inclusive(char::is_whitespace)
+        .map(|word| {
+            let text = strip_move_number(word);
+            if text.starts_with("0-0") {
+                let number = &word[..word.len() - text.len()];
+                format!("{}{}", number, text.replace('0', "O"))
+            } else {
+                word.to_string()
+            }
+        })
+        .collect()
+}
+
+// Move numbers may be written apart from or stuck to the move, like "12." or "12...e5". Only
+// digits followed by dots are a move number, so "0-0" keeps its zeros.
+fn strip_move_number(word: &str) -> &str {
+    let digits = word.len() - word.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    match word[digits..].strip_prefix('.') {
+        Some(rest) if digits > 0 => rest.trim_start_matches('.'),
+        _ => word,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Board, ChessError, Game, Square};
+
+    #[test]
+    fn test_pgn_round_trip() -> Result<(), ChessError> {
+        let pgn = "[Event \"Casual \\\"blitz\\\"\"]\n\
+                   [Site \"?\"]\n\
+                   [Date \"2024.01.01\"]\n\
+                   [Round \"?\"]\n\
+                   [White \"A\"]\n\
+                   [Black \"B\"]\n\
+                   [Result \"1-0\"]\n\
+                   [TimeControl \"300\"]\n\
+                   \n\
+                   {Opening} 1. e4 {[%clk 0:04:58] [%eval 0.3]} 1... e5 (1... c5 $1 2. Nf3 (2. c3)\n\
+                   2... d6) (1... e6?! {French}) 2. Nf3!? $14 Nc6 3. Bb5 {Spanish} 3... a6 1-0\n";
+        let game = Game::from_pgn(pgn)?;
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(game.tag("TimeControl"), Some("300"));
+        assert_eq!(game.result(), "1-0");
+        assert_eq!(game.to_pgn()?, pgn);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_annotations() -> Result<(), ChessError> {
+        let game = Game::from_pgn(
+            "[Event \"?\"]\n\n{Start} 1. e4 {[%clk 0:04:58.5] [%eval -0.25]} \
+             e5 $2 (1... c5! ; Sicilian\n 2. Nf3) 2. Nf3?? *",
+        )?;
+        let tree = game.tree();
+        assert_eq!(tree.node(&[0]).unwrap().comments_before, vec!["Start"]);
+        let e4 = tree.node(&[0]).unwrap();
+        assert_eq!(e4.clock(), Some(Duration::from_millis(298_500)));
+        assert_eq!(e4.eval(), Some("-0.25"));
+        assert_eq!(tree.node(&[0, 0]).unwrap().nags, vec![2]);
+        let c5 = tree.node(&[0, 1]).unwrap();
+        assert_eq!(c5.record().to, Square::C5);
+        assert_eq!(c5.suffix.as_deref(), Some("!"));
+        assert_eq!(c5.comments, vec!["Sicilian"]);
+        assert_eq!(tree.node(&[0, 1, 0]).unwrap().record().to, Square::F3);
+        assert_eq!(tree.node(&[0, 0, 0]).unwrap().suffix.as_deref(), Some("??"));
+        assert_eq!(tree.mainline(&[]).len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_from_fen() -> Result<(), ChessError> {
+        let pgn = "[Event \"?\"]\n\
+                   [Site \"?\"]\n\
+                   [Date \"????.??.??\"]\n\
+                   [Round \"?\"]\n\
+                   [White \"?\"]\n\
+                   [Black \"?\"]\n\
+                   [Result \"*\"]\n\
+                   [SetUp \"1\"]\n\
+                   [FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n\
+                   \n\
+                   1... Kd7 (1... Ke7 2. e4) 2. e4 *\n";
+        let game = Game::from_pgn(pgn)?;
+        assert_eq!(game.to_pgn()?, pgn);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_chess960() -> Result<(), ChessError> {
+        let pgn = "[Event \"?\"]\n\
+                   [Site \"?\"]\n\
+                   [Date \"????.??.??\"]\n\
+                   [Round \"?\"]\n\
+                   [White \"?\"]\n\
+                   [Black \"?\"]\n\
+                   [Result \"*\"]\n\
+                   [Variant \"Chess960\"]\n\
+                   [SetUp \"1\"]\n\
+                   [FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\"]\n\
+                   \n\
+                   1. e4 e5 2. Nf3 Nf6 3. Bc4 Bc5 4. O-O O-O *\n";
+        let game = Game::from_pgn(pgn)?;
+        let board = game.board();
+        assert!(board.is_chess960());
+        assert_eq!(board.move_list()[6].to, Square::H1);
+        assert_eq!(game.to_pgn()?, pgn);
+        assert_eq!(Game::from_board(board)?.to_pgn()?, pgn);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_lenient_moves() -> Result<(), ChessError> {
+        let moves = "1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. 0-0 Be7 5. d3 0-0 *";
+        let game = Game::from_pgn(moves)?;
+        assert!(game.board().move_list()[6].castling);
+        assert!(game.board().move_list()[9].castling);
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. 0-0")?;
+        assert_eq!(board.move_list()[6].to, Square::G1);
+
+        let game = Game::from_pgn("1. e4 Nf6 2. e5 d5 3. exd6 e.p. *")?;
+        assert_eq!(game.board().move_list()[4].to, Square::D6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_import_errors() {
+        assert!(Game::from_pgn("1. e4 (e5").is_err());
+        assert!(Game::from_pgn("1. e4 e5)").is_err());
+        assert!(Game::from_pgn("(1. e4)").is_err());
+        assert!(Game::from_pgn("1. e4 {unterminated").is_err());
+        assert!(Game::from_pgn("1. e5").is_err());
+        assert!(Game::from_pgn("[Event \"?] 1. e4").is_err());
+        assert!(Game::from_pgn("1. e4 * e5").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::{ChessError, Game};
+
+// A game that could not be read, located by its position in the stream and, when a move
+// could not be played, by the move's ply in its line and its SAN text
+#[derive(Debug)]
+pub struct PgnReadError {
+    pub game_index: usize,
+    pub byte_offset: u64,
+    pub ply: Option<usize>,
+    pub san: Option<String>,
+    pub error: ChessError,
+}
+
+impl fmt::Display for PgnReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Game {} at byte {}", self.game_index, self.byte_offset)?;
+        if let (Some(ply), Some(san)) = (self.ply, &self.san) {
+            write!(f, ", ply {} ({})", ply, san)?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+// Reads the games of a PGN database one at a time, holding only the current game in memory.
+// A game that fails to parse is reported and the reader moves on to the next one.
+pub struct PgnReader<R: Read> {
+    reader: BufReader<R>,
+    offset: u64,
+    game_index: usize,
+    // The first line of the next game, read while looking for the end of the previous one
+    pending_line: Option<String>,
+    done: bool,
+}
+
+impl<R: Read> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader {
+            reader: BufReader::new(reader),
+            offset: 0,
+            game_index: 0,
+            pending_line: None,
+            done: false,
+        }
+    }
+
+    fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        let mut bytes = Vec::new();
+        if self.reader.read_until(b'\n', &mut bytes)? == 0 {
+            return Ok(None);
+        }
+        self.offset += bytes.len() as u64;
+        Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    // The text of the next game and its byte offset. A game ends at its termination marker,
+    // or where a tag pair follows its movetext.
+    fn read_game_text(&mut self) -> std::io::Result<Option<(String, u64)>> {
+        let mut text = String::new();
+        let mut start = self.offset;
+        let mut in_movetext = false;
+        let mut in_comment = false;
+        while let Some(line) = match self.pending_line.take() {
+            Some(line) => Some(line),
+            None => self.read_line()?,
+        } {
+            let trimmed = line.trim();
+            if text.trim().is_empty() {
+                start = self.offset - line.len() as u64;
+            }
+            if !in_comment && trimmed.starts_with('[') {
+                if in_movetext {
+                    self.pending_line = Some(line);
+                    return Ok(Some((text, start)));
+                }
+            } else if !trimmed.is_empty() && !trimmed.starts_with('%') {
+                in_movetext = true;
+                if let Some(end) = Self::find_game_end(&line, &mut in_comment) {
+                    text.push_str(&line[..end]);
+                    // Another game may follow on the same line
+                    if !line[end..].trim().is_empty() {
+                        self.pending_line = Some(line[end..].to_string());
+                    }
+                    return Ok(Some((text, start)));
+                }
+            }
+            text.push_str(&line);
+        }
+        if text.trim().is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some((text, start)))
+        }
+    }
+
+    // The byte offset just past the game termination marker of a movetext line, if it has
+    // one. Keeps track of whether a brace comment is still open at the end of the line.
+    fn find_game_end(line: &str, in_comment: &mut bool) -> Option<usize> {
+        const TERMINATION_MARKERS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+        let mut word_start = None;
+        for (i, c) in line
+            .char_indices()
+            .chain(std::iter::once((line.len(), ' ')))
+        {
+            if *in_comment {
+                *in_comment = c != '}';
+            } else if c.is_whitespace() || "{;()".contains(c) {
+                if let Some(word_start) = word_start.take() {
+                    if TERMINATION_MARKERS.contains(&&line[word_start..i]) {
+                        return Some(i);
+                    }
+                }
+                match c {
+                    '{' => *in_comment = true,
+                    ';' => break,
+                    _ => {}
+                }
+            } else if word_start.is_none() {
+                word_start = Some(i);
+            }
+        }
+        None
+    }
+}
+
+impl<R: Read> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let game_index = self.game_index;
+        let result = match self.read_game_text() {
+            Ok(Some((text, byte_offset))) => {
+                let mut failed_move = None;
+                Game::read_pgn(&text, &mut failed_move).map_err(|error| {
+                    let (ply, san) = failed_move.unzip();
+                    PgnReadError {
+                        game_index,
+                        byte_offset,
+                        ply,
+                        san,
+                        error,
+                    }
+                })
+            }
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(error) => {
+                self.done = true;
+                Err(PgnReadError {
+                    game_index,
+                    byte_offset: self.offset,
+                    ply: None,
+                    san: None,
+                    error: ChessError::IoError(error),
+                })
+            }
+        };
+        self.game_index += 1;
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChessError, PgnReader};
+
+    const DATABASE: &str = "[Event \"One\"]\n\
+                            [Result \"1-0\"]\n\
+                            \n\
+                            1. e4 e5 {a comment\n\
+                            [spanning lines]} 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n\
+                            \n\
+                            [Event \"Two\"]\n\
+                            \n\
+                            1. e4 e4 *\n\
+                            \n\
+                            [Event \"Three\"]\n\
+                            [Result \"0-1\"]\n\
+                            \n\
+                            1. f3 e5\n\
+                            2. g4 Qh4# 0-1\n";
+
+    #[test]
+    fn test_read_games() -> Result<(), ChessError> {
+        let results: Vec<_> = PgnReader::new(DATABASE.as_bytes()).collect();
+        assert_eq!(results.len(), 3);
+
+        let first = results[0].as_ref().unwrap();
+        assert_eq!(first.tag("Event"), Some("One"));
+        assert_eq!(first.result(), "1-0");
+        assert_eq!(first.board().move_list().len(), 7);
+
+        let error = results[1].as_ref().err().unwrap();
+        assert_eq!(error.game_index, 1);
+        assert_eq!(
+            error.byte_offset,
+            DATABASE.find("[Event \"Two\"]").unwrap() as u64
+        );
+        assert!(matches!(error.error, ChessError::InvalidSan(_)));
+        assert_eq!(error.ply, Some(2));
+        assert_eq!(error.san.as_deref(), Some("e4"));
+        assert_eq!(
+            error.to_string(),
+            "Game 1 at byte 104, ply 2 (e4): No legal move matches 'e4'"
+        );
+
+        let third = results[2].as_ref().unwrap();
+        assert_eq!(third.tag("Event"), Some("Three"));
+        assert_eq!(third.result(), "0-1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tagless_games() {
+        let database = "1. e4 e5 2. Nf3 {White wins 1-0 later} Nc6 1-0\n\
+                        1. d4 d5 0-1 1. c4 *\n\
+                        1. f3 e5 2. g4 Qh4# 0-1\n";
+        let games: Vec<_> = PgnReader::new(database.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(games.len(), 4);
+        let moves: Vec<_> = games.iter().map(|g| g.board().move_list().len()).collect();
+        assert_eq!(moves, vec![4, 2, 1, 4]);
+        assert_eq!(games[0].result(), "1-0");
+        assert_eq!(games[1].result(), "0-1");
+        assert_eq!(games[2].result(), "*");
+        assert_eq!(games[3].result(), "0-1");
+    }
+
+    #[test]
+    fn test_read_empty_input() {
+        assert_eq!(PgnReader::new("\n\n".as_bytes()).count(), 0);
+    }
+}
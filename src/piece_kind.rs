@@ -0,0 +1,77 @@
+use chess_pgn_parser::Piece;
+
+// What a piece moves like, as opposed to which piece it is (`UniquePiece`). A promoted pawn
+// keeps its identity but changes kind.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    pub const ALL: [PieceKind; 6] = [
+        PieceKind::Pawn,
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+        PieceKind::King,
+    ];
+
+    // What a pawn may promote to, best first
+    pub const PROMOTIONS: [PieceKind; 4] = [
+        PieceKind::Queen,
+        PieceKind::Rook,
+        PieceKind::Bishop,
+        PieceKind::Knight,
+    ];
+
+    // Position in `ALL`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// The PGN parser has its own piece type
+impl From<Piece> for PieceKind {
+    fn from(piece: Piece) -> Self {
+        match piece {
+            Piece::Pawn => PieceKind::Pawn,
+            Piece::Knight => PieceKind::Knight,
+            Piece::Bishop => PieceKind::Bishop,
+            Piece::Rook => PieceKind::Rook,
+            Piece::Queen => PieceKind::Queen,
+            Piece::King => PieceKind::King,
+        }
+    }
+}
+
+impl From<PieceKind> for Piece {
+    fn from(kind: PieceKind) -> Self {
+        match kind {
+            PieceKind::Pawn => Piece::Pawn,
+            PieceKind::Knight => Piece::Knight,
+            PieceKind::Bishop => Piece::Bishop,
+            PieceKind::Rook => Piece::Rook,
+            PieceKind::Queen => Piece::Queen,
+            PieceKind::King => Piece::King,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Piece, PieceKind};
+
+    #[test]
+    fn test_piece_kind() {
+        for (index, kind) in PieceKind::ALL.iter().enumerate() {
+            assert_eq!(kind.index(), index);
+            assert_eq!(PieceKind::from(Piece::from(*kind)), *kind);
+        }
+    }
+}
@@ -1,48 +1,193 @@
+use std::borrow::Cow;
+
 use crate::{Board, Position};
+
+// Walks the positions of a game, from the starting position (ply 0) to the position after the
+// last move. Each step plays or takes back a single move on a working copy of the board.
+// Iterating a `&mut` cursor moves it forward, iterating it from the back moves it backward,
+// and either way leaves it usable afterwards.
 pub struct PositionCursor<'a> {
-    board: &'a Board,
-    pos_num: usize,
+    board: Cow<'a, Board>,
+    move_count: usize,
+    ply: usize,
 }
 
 impl<'a> PositionCursor<'a> {
-    pub fn new(board: &'a Board) -> PositionCursor {
+    pub fn new(board: &'a Board) -> PositionCursor<'a> {
+        Self::from_cow(Cow::Borrowed(board))
+    }
+
+    // A cursor over a board built on the fly, like the end of a variation
+    pub(crate) fn from_owned(board: Board) -> PositionCursor<'a> {
+        Self::from_cow(Cow::Owned(board))
+    }
+
+    fn from_cow(board: Cow<'a, Board>) -> PositionCursor<'a> {
+        let move_count = board.position_count() - 1;
         PositionCursor {
             board,
-            pos_num: board.position_count(),
+            move_count,
+            ply: move_count,
         }
     }
 
-    fn generate_position(&self) -> Option<Position> {
-        if self.pos_num <= self.board.position_count() && self.pos_num > 0 {
-            let mut pos = Position::new();
-            for piece in self.board.pieces.iter() {
-                if let Some(square) = piece.curr_square() {
-                    pos.insert((piece.piece, piece.white), square.clone());
-                }
+    // Number of moves played before the current position
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    pub fn curr(&self) -> Position {
+        let mut pos = Position::new();
+        for piece in self.board.pieces.iter() {
+            if let Some(square) = piece.curr_square() {
+                pos.insert((piece.piece, piece.color), square.clone());
             }
-            return Some(pos);
         }
-        None
+        pos
+    }
+
+    // Moves to the position after `ply` moves, leaving the cursor in place if there is none
+    pub fn seek(&mut self, ply: usize) -> Option<Position> {
+        if ply > self.move_count {
+            return None;
+        }
+        // The board is only copied once the cursor first moves
+        while self.ply > ply {
+            self.board.to_mut().undo()?;
+            self.ply -= 1;
+        }
+        while self.ply < ply {
+            self.board.to_mut().redo()?;
+            self.ply += 1;
+        }
+        Some(self.curr())
+    }
+
+    pub fn first(&mut self) -> Position {
+        self.seek(0).expect("the starting position is always there")
+    }
+
+    // The position after the last move
+    pub fn last(&mut self) -> Position {
+        self.seek(self.move_count)
+            .expect("the last position is always there")
+    }
+
+    pub fn prev(&mut self) -> Option<Position> {
+        self.seek(self.ply.checked_sub(1)?)
     }
 
-    pub fn curr(&mut self) -> Position {
-        self.pos_num = self.board.position_count();
-        self.generate_position().unwrap()
+    // Iterator is implemented for `&mut PositionCursor` instead, so that iterating doesn't
+    // consume the cursor and `Iterator::last` doesn't hide `last`
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Position> {
+        self.seek(self.ply + 1)
     }
+}
+
+impl Iterator for &mut PositionCursor<'_> {
+    type Item = Position;
 
     fn next(&mut self) -> Option<Position> {
-        if self.pos_num < self.board.position_count() {
-            self.pos_num += 1;
-            return self.generate_position();
-        }
-        None
+        PositionCursor::next(self)
     }
 
-    fn prev(&mut self) -> Option<Position> {
-        if self.pos_num > 0 {
-            self.pos_num -= 1;
-            return self.generate_position();
-        }
-        None
+    // Iterating from the front reaches the last position and from the back the first one, so
+    // the hint covers both
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (forward, backward) = (self.move_count - self.ply, self.ply);
+        (forward.min(backward), Some(forward.max(backward)))
+    }
+}
+
+impl DoubleEndedIterator for &mut PositionCursor<'_> {
+    fn next_back(&mut self) -> Option<Position> {
+        self.prev()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, ChessError, Color, Square, UniquePiece};
+
+    #[test]
+    fn test_cursor_walks_history() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 d5 2. exd5 Qxd5")?;
+        let mut cursor = board.position_cursor();
+        assert_eq!(cursor.ply(), 4);
+        assert!(cursor.next().is_none());
+
+        let pos = cursor.first();
+        assert_eq!(pos.len(), 32);
+        assert_eq!(
+            pos.get(&(UniquePiece::EPawn, Color::White)),
+            Some(&Square::E2)
+        );
+        assert!(cursor.prev().is_none());
+
+        let pos = cursor.next().unwrap();
+        assert_eq!(
+            pos.get(&(UniquePiece::EPawn, Color::White)),
+            Some(&Square::E4)
+        );
+
+        let pos = cursor.seek(3).unwrap();
+        assert_eq!(
+            pos.get(&(UniquePiece::EPawn, Color::White)),
+            Some(&Square::D5)
+        );
+        assert!(!pos.contains_key(&(UniquePiece::DPawn, Color::Black)));
+        assert!(cursor.seek(5).is_none());
+        assert_eq!(cursor.ply(), 3);
+
+        let pos = cursor.prev().unwrap();
+        assert_eq!(
+            pos.get(&(UniquePiece::DPawn, Color::Black)),
+            Some(&Square::D5)
+        );
+        assert_eq!(cursor.ply(), 2);
+        let pos = cursor.last();
+        assert_eq!(
+            pos.get(&(UniquePiece::Queen, Color::Black)),
+            Some(&Square::D5)
+        );
+
+        // Jumping to the end leaves the cursor free to keep navigating
+        assert_eq!(cursor.ply(), 4);
+        let pos = cursor.prev().unwrap();
+        assert_eq!(
+            pos.get(&(UniquePiece::Queen, Color::Black)),
+            Some(&Square::D8)
+        );
+        assert_eq!(cursor.ply(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cursor_iterates_both_ways() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5 2. Nf3")?;
+        let mut cursor = board.position_cursor();
+        let positions = &mut cursor;
+        assert_eq!(positions.size_hint(), (0, Some(3)));
+        positions.first();
+        assert_eq!(positions.size_hint(), (0, Some(3)));
+        positions.seek(1);
+        assert_eq!(positions.size_hint(), (1, Some(2)));
+        positions.first();
+        let knight_squares: Vec<_> = positions
+            .map(|pos| pos[&(UniquePiece::KKnight, Color::White)].clone())
+            .collect();
+        assert_eq!(knight_squares, vec![Square::G1, Square::G1, Square::F3]);
+        assert_eq!(cursor.ply(), 3);
+
+        let pawn_squares: Vec<_> = (&mut cursor)
+            .rev()
+            .map(|pos| pos[&(UniquePiece::EPawn, Color::Black)].clone())
+            .collect();
+        assert_eq!(pawn_squares, vec![Square::E5, Square::E7, Square::E7]);
+        assert_eq!(cursor.ply(), 0);
+        Ok(())
     }
 }
@@ -1,5 +1,7 @@
-use chess_pgn_parser::{Square, File, Rank};
+use chess_pgn_parser::Square;
+use crate::PieceKind;
 use super::{PieceRules, PieceData, Board};
+#[derive(Clone)]
 pub struct KingRules;
 
 impl KingRules {
@@ -9,9 +11,8 @@ impl KingRules {
 }
 
 impl PieceRules for KingRules {
-    fn get_initial_square(&self, piece_data: &PieceData) -> Square {
-        let rank = if piece_data.white {Rank::R1} else {Rank::R8};
-        Square::new_known(File::E, rank)
+    fn kind(&self) -> PieceKind {
+        PieceKind::King
     }
 
     fn get_move_only_squares(&self, piece_data: &PieceData) -> Vec<Square> { 
@@ -25,10 +26,13 @@ impl PieceRules for KingRules {
         if let Some(new_square) = curr_square.new_with_offset(-1, -1) {squares.push(new_square);}
         if let Some(new_square) = curr_square.new_with_offset(-1, 0) {squares.push(new_square);}
         if let Some(new_square) = curr_square.new_with_offset(-1, 1) {squares.push(new_square);}
-        // TODO: add castling
         squares
     }
 
+    fn get_special_squares(&self, piece_data: &PieceData, board: &Board) -> Vec<Square> {
+        board.get_castling_squares(piece_data.color)
+    }
+
     fn get_capture_only_squares(&self, piece_data: &PieceData) -> Vec<Square> { 
         self.get_move_only_squares(piece_data)
     }
@@ -1,5 +1,7 @@
-use chess_pgn_parser::{Square, File, Rank};
-use super::{PieceRules, PieceData, UniquePiece, Board};
+use chess_pgn_parser::Square;
+use crate::PieceKind;
+use super::{PieceRules, PieceData, Board};
+#[derive(Clone)]
 pub struct KnightRules;
 
 impl KnightRules {
@@ -9,14 +11,8 @@ impl KnightRules {
 }
 
 impl PieceRules for KnightRules {
-    fn get_initial_square(&self, piece_data: &PieceData) -> Square {
-        let rank = if piece_data.white {Rank::R1} else {Rank::R8};
-        let file = match piece_data.piece {
-            UniquePiece::QKnight => File::B,
-            UniquePiece::KKnight => File::G,
-            _ => panic!("Invalid UniquePiece for pawn initial square")
-        };
-        Square::new_known(file, rank)
+    fn kind(&self) -> PieceKind {
+        PieceKind::Knight
     }
 
     fn get_move_only_squares(&self, piece_data: &PieceData) -> Vec<Square> {
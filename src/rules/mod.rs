@@ -1,4 +1,6 @@
-use chess_pgn_parser::{Square};
+use chess_pgn_parser::{File, Square};
+use crate::PieceKind;
+use crate::square_index;
 use crate::piece_data::{PieceData};
 use super::Board;
 
@@ -22,57 +24,116 @@ pub enum UniquePiece {
     QRook, QKnight, QBishop, Queen, King, KBishop, KKnight, KRook
 }
 
-pub trait PieceRules {
-    fn get_initial_square(&self, piece_data: &PieceData) -> Square;
+impl UniquePiece {
+    pub const ALL: [UniquePiece; 16] = [
+        UniquePiece::APawn, UniquePiece::BPawn, UniquePiece::CPawn, UniquePiece::DPawn,
+        UniquePiece::EPawn, UniquePiece::FPawn, UniquePiece::GPawn, UniquePiece::HPawn,
+        UniquePiece::QRook, UniquePiece::QKnight, UniquePiece::QBishop, UniquePiece::Queen,
+        UniquePiece::King, UniquePiece::KBishop, UniquePiece::KKnight, UniquePiece::KRook
+    ];
+
+    // The type the piece starts the game as. Promoted pawns keep their pawn identity.
+    pub fn initial_piece(&self) -> PieceKind {
+        match self {
+            UniquePiece::QRook | UniquePiece::KRook => PieceKind::Rook,
+            UniquePiece::QKnight | UniquePiece::KKnight => PieceKind::Knight,
+            UniquePiece::QBishop | UniquePiece::KBishop => PieceKind::Bishop,
+            UniquePiece::Queen => PieceKind::Queen,
+            UniquePiece::King => PieceKind::King,
+            _ => PieceKind::Pawn
+        }
+    }
+
+    pub fn initial_file(&self) -> File {
+        match self {
+            UniquePiece::APawn | UniquePiece::QRook => File::A,
+            UniquePiece::BPawn | UniquePiece::QKnight => File::B,
+            UniquePiece::CPawn | UniquePiece::QBishop => File::C,
+            UniquePiece::DPawn | UniquePiece::Queen => File::D,
+            UniquePiece::EPawn | UniquePiece::King => File::E,
+            UniquePiece::FPawn | UniquePiece::KBishop => File::F,
+            UniquePiece::GPawn | UniquePiece::KKnight => File::G,
+            UniquePiece::HPawn | UniquePiece::KRook => File::H
+        }
+    }
+}
+
+pub fn new_rules(piece: PieceKind) -> Box<dyn PieceRules> {
+    match piece {
+        PieceKind::Pawn => Box::new(PawnRules::new()),
+        PieceKind::Rook => Box::new(RookRules::new()),
+        PieceKind::Knight => Box::new(KnightRules::new()),
+        PieceKind::Bishop => Box::new(BishopRules::new()),
+        PieceKind::Queen => Box::new(QueenRules::new()),
+        PieceKind::King => Box::new(KingRules::new())
+    }
+}
+
+pub trait PieceRulesClone {
+    fn clone_box(&self) -> Box<dyn PieceRules>;
+}
+
+impl<T: 'static + PieceRules + Clone> PieceRulesClone for T {
+    fn clone_box(&self) -> Box<dyn PieceRules> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn PieceRules> {
+    fn clone(&self) -> Box<dyn PieceRules> {
+        self.clone_box()
+    }
+}
+
+pub trait PieceRules: PieceRulesClone + Send + Sync {
+    fn kind(&self) -> PieceKind;
     fn get_move_only_squares(&self, _piece_data: &PieceData) -> Vec<Square> { Vec::new() }
     fn get_capture_only_squares(&self, _piece_data: &PieceData) -> Vec<Square> { Vec::new() }
     fn get_single_move_or_capture_squares(&self, _piece_data: &PieceData) -> Vec<Square> { Vec::new() }
 
+    // Moves that depend on board state beyond occupancy, like castling
+    fn get_special_squares(&self, _piece_data: &PieceData, _board: &Board) -> Vec<Square> { Vec::new() }
+
     // Override with empty square Vec to disable
     fn get_diagonal_squares(&self, piece_data: &PieceData, board: &Board) -> Vec<Square> {
-        let mut squares = self.get_linear_squares_with_offsets(piece_data, board, 1, 1);
-        squares.extend(self.get_linear_squares_with_offsets(piece_data, board, 1, -1));
-        squares.extend(self.get_linear_squares_with_offsets(piece_data, board, -1, -1));
-        squares.extend(self.get_linear_squares_with_offsets(piece_data, board, -1, 1));
-
-        squares
+        board.get_slider_squares(piece_data, true)
     }
     
     // Override with empty square Vec to disable
     fn get_straight_squares(&self, piece_data: &PieceData, board: &Board) -> Vec<Square> {
-        let mut squares = self.get_linear_squares_with_offsets(piece_data, board, 0, 1);
-        squares.extend(self.get_linear_squares_with_offsets(piece_data, board, 0, -1));
-        squares.extend(self.get_linear_squares_with_offsets(piece_data, board, 1, 0));
-        squares.extend(self.get_linear_squares_with_offsets(piece_data, board, -1, 0));
-
-        squares
+        board.get_slider_squares(piece_data, false)
     }
     
+    // The squares along one ray, given by a one-square step such as (1, 1), up to and including the first enemy piece
+    #[deprecated(note = "use get_diagonal_squares or get_straight_squares, which cover every ray at once")]
     fn get_linear_squares_with_offsets(&self, piece_data: &PieceData, board: &Board, file_offset: i32, rank_offset: i32) -> Vec<Square> {
-        let mut squares = Vec::new();
-        let mut curr_square = piece_data.curr_square().unwrap().clone();
-        loop {
-            if let Some(new_square) = curr_square.new_with_offset(file_offset, rank_offset) {
-                match board.get_piece_data_at_square(&new_square) {
-                    Some(piece_at_square) if piece_at_square.white != piece_data.white => {
-                        squares.push(new_square);
-                        break;
-                    },
-                    None => {
-                        curr_square = new_square.clone();
-                        squares.push(new_square);
-                    },
-                    _ => break
-                }
-            } else {
-                break;
-            }
-        }
+        let start = match piece_data.curr_square().and_then(square_index::square_coords) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        board.get_slider_squares(piece_data, file_offset != 0 && rank_offset != 0).into_iter()
+            .filter(|square| {
+                let (file, rank) = square_index::square_coords(square).unwrap();
+                (file - start.0).signum() == file_offset.signum() && (rank - start.1).signum() == rank_offset.signum()
+            }).collect()
+    }
+
+    // Squares this piece threatens, regardless of whether a capture is currently possible there
+    fn get_attacked_squares(&self, piece_data: &PieceData, board: &Board) -> Vec<Square> {
+        let mut squares = self.get_diagonal_squares(piece_data, board);
+        squares.extend(self.get_straight_squares(piece_data, board));
+        squares.extend(self.get_capture_only_squares(piece_data));
 
         squares
     }
 
+    // Legal squares: pseudo-legal squares that don't leave the mover's king in check
     fn get_valid_squares(&self, piece_data: &PieceData, board: &Board) -> Vec<Square> {
+        let squares = self.get_pseudo_legal_squares(piece_data, board);
+        board.filter_legal_squares(piece_data, squares)
+    }
+
+    fn get_pseudo_legal_squares(&self, piece_data: &PieceData, board: &Board) -> Vec<Square> {
         let mut squares = self.get_diagonal_squares(piece_data, board);
         squares.extend(self.get_straight_squares(piece_data, board));
         let move_only_squares: Vec<Square> = self.get_move_only_squares(piece_data).into_iter()
@@ -81,14 +142,15 @@ pub trait PieceRules {
         let capture_only_squares: Vec<Square> = self.get_capture_only_squares(piece_data).into_iter()
             .filter(|square| {
                 if let Some(piece) = board.get_piece_data_at_square(square) {
-                    return piece.white != piece_data.white;
+                    return piece.color != piece_data.color;
                 }
 
-                return false;
+                false
             }).collect();
 
-        squares.extend(move_only_squares.into_iter());
-        squares.extend(capture_only_squares.into_iter());
+        squares.extend(move_only_squares);
+        squares.extend(capture_only_squares);
+        squares.extend(self.get_special_squares(piece_data, board));
 
         squares
     }
@@ -108,6 +170,17 @@ mod tests {
         assert_eq!(2, valid_squares.len());
     }
     
+    #[test]
+    #[allow(deprecated)]
+    fn test_linear_squares_with_offsets() {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5").unwrap();
+        let queen = board.get_piece_data_at_square(&Square::D1).expect("missing piece.");
+        let ray = queen.behavior.get_linear_squares_with_offsets(queen, &board, 1, 1);
+        assert_eq!(ray, vec![Square::E2, Square::F3, Square::G4, Square::H5]);
+        assert!(queen.behavior.get_linear_squares_with_offsets(queen, &board, 0, 1).is_empty());
+    }
+    
     #[test]
     fn test_rook_behavior() {
         let board = Board::new();
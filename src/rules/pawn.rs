@@ -1,5 +1,7 @@
-use chess_pgn_parser::{Square, File, Rank};
-use super::{PieceRules, PieceData, UniquePiece, Board};
+use chess_pgn_parser::{Square, Rank};
+use crate::PieceKind;
+use super::{PieceRules, PieceData, Board};
+#[derive(Clone)]
 pub struct PawnRules;
 
 impl PawnRules {
@@ -9,30 +11,14 @@ impl PawnRules {
 }
 
 impl PieceRules for PawnRules {
-    fn get_initial_square(&self, piece_data: &PieceData) -> Square {
-        let rank = if piece_data.white {Rank::R2} else {Rank::R7};
-        let file = match piece_data.piece {
-            UniquePiece::APawn => File::A,
-            UniquePiece::BPawn => File::B,
-            UniquePiece::CPawn => File::C,
-            UniquePiece::DPawn => File::D,
-            UniquePiece::EPawn => File::E,
-            UniquePiece::FPawn => File::F,
-            UniquePiece::GPawn => File::G,
-            UniquePiece::HPawn => File::H,
-            _ => panic!("Invalid UniquePiece for pawn initial square")
-        };
-        Square::new_known(file, rank)
+    fn kind(&self) -> PieceKind {
+        PieceKind::Pawn
     }
 
     fn get_move_only_squares(&self, piece_data: &PieceData) -> Vec<Square> {
         let mut squares: Vec<Square> = Vec::new();
-        let direction = if piece_data.white {1} else {-1};
+        let direction = if piece_data.color.is_white() {1} else {-1};
         let curr_square = piece_data.curr_square().unwrap();
-        if !piece_data.has_moved() {
-            let double_step_square = curr_square.new_with_offset(0, 2 * direction).unwrap();
-            squares.push(double_step_square);
-        }
 
         // Always unwrap because there can't be any un-promoted pawns in the last rank
         let single_step_square = curr_square.new_with_offset(0, direction).unwrap();
@@ -43,7 +29,7 @@ impl PieceRules for PawnRules {
 
     fn get_capture_only_squares(&self, piece_data: &PieceData) -> Vec<Square> {
         let mut squares: Vec<Square> = Vec::new();
-        let direction = if piece_data.white {1} else {-1};
+        let direction = if piece_data.color.is_white() {1} else {-1};
         let curr_square = piece_data.curr_square().unwrap();
 
         if let Some(right_capture_square) = curr_square.new_with_offset(1, direction) {
@@ -53,8 +39,33 @@ impl PieceRules for PawnRules {
         if let Some(left_capture_square) = curr_square.new_with_offset(-1, direction) {
             squares.push(left_capture_square);
         }
-        
-        // TODO: en passant
+
+        squares
+    }
+
+    fn get_special_squares(&self, piece_data: &PieceData, board: &Board) -> Vec<Square> {
+        let mut squares: Vec<Square> = Vec::new();
+        let direction = if piece_data.color.is_white() {1} else {-1};
+        let curr_square = piece_data.curr_square().unwrap();
+
+        // The double step can't jump over a piece
+        let start_rank = if piece_data.color.is_white() {Rank::R2} else {Rank::R7};
+        if curr_square.rank() == Some(start_rank) {
+            let single_step_square = curr_square.new_with_offset(0, direction).unwrap();
+            let double_step_square = curr_square.new_with_offset(0, 2 * direction).unwrap();
+            if board.get_piece_data_at_square(&single_step_square).is_none()
+                && board.get_piece_data_at_square(&double_step_square).is_none() {
+                squares.push(double_step_square);
+            }
+        }
+
+        let en_passant_rank = if piece_data.color.is_white() {Rank::R6} else {Rank::R3};
+        if let Some(en_passant_square) = board.en_passant_square() {
+            if en_passant_square.rank() == Some(en_passant_rank)
+                && self.get_capture_only_squares(piece_data).contains(en_passant_square) {
+                squares.push(en_passant_square.clone());
+            }
+        }
 
         squares
     }
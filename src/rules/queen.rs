@@ -1,5 +1,6 @@
-use chess_pgn_parser::{Square, File, Rank};
-use super::{PieceRules, PieceData};
+use crate::PieceKind;
+use super::{PieceRules};
+#[derive(Clone)]
 pub struct QueenRules;
 
 impl QueenRules {
@@ -9,8 +10,7 @@ impl QueenRules {
 }
 
 impl PieceRules for QueenRules {
-    fn get_initial_square(&self, piece_data: &PieceData) -> Square {
-        let rank = if piece_data.white {Rank::R1} else {Rank::R8};
-        Square::new_known(File::D, rank)
+    fn kind(&self) -> PieceKind {
+        PieceKind::Queen
     }
 }
@@ -0,0 +1,271 @@
+use chess_pgn_parser::Square;
+
+use crate::square_index;
+use crate::{Board, ChessError, Color, Move, Outcome, PieceKind};
+
+impl Board {
+    // Standard Algebraic Notation for a legal move of the side to move, like "Nbd2",
+    // "exd6", "e8=Q+" or "O-O#"
+    pub fn to_san(
+        &self,
+        from: &Square,
+        to: &Square,
+        promoted_to: Option<PieceKind>,
+    ) -> Result<String, ChessError> {
+        self.get_san(from, to, promoted_to, true)
+    }
+
+    // SAN, optionally leaving out the 'from' qualifier of pieces
+    fn get_san(
+        &self,
+        from: &Square,
+        to: &Square,
+        promoted_to: Option<PieceKind>,
+        disambiguate: bool,
+    ) -> Result<String, ChessError> {
+        let piece_data = self
+            .get_piece_data_at_square(from)
+            .ok_or_else(|| ChessError::EmptySquare(from.clone()))?;
+        let piece = piece_data.behavior.kind();
+        let color = piece_data.color;
+
+        // Playing the move on a copy validates it and tells us about check and mate
+        let mut next_board = self.clone();
+        next_board.simple_move(from, to, promoted_to)?;
+
+        let (from_file, _) =
+            square_index::square_coords(from).ok_or(ChessError::IncorrectMoveParameters)?;
+        let to_name = square_index::square_name(to).ok_or(ChessError::IncorrectMoveParameters)?;
+
+        let mut san = String::new();
+        if let Some(king_side) = self.get_castling_side(piece_data, to) {
+            san.push_str(if king_side { "O-O" } else { "O-O-O" });
+        } else {
+            let is_capture =
+                self.get_piece_data_at_square(to).is_some() || self.is_en_passant(piece_data, to);
+            if piece == PieceKind::Pawn {
+                if is_capture {
+                    san.push(Self::file_char(from_file));
+                }
+            } else {
+                san.push(Self::piece_to_char(piece, Color::White));
+                if disambiguate {
+                    san.push_str(&self.get_disambiguation(piece, color, from, to));
+                }
+            }
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&to_name);
+            if let Some(promotion) = promoted_to {
+                san.push('=');
+                san.push(Self::piece_to_char(promotion, Color::White));
+            }
+        }
+
+        if let Some(Outcome::Checkmate { .. }) = next_board.outcome() {
+            san.push('#');
+        } else if next_board.is_in_check(!color) {
+            san.push('+');
+        }
+        Ok(san)
+    }
+
+    // The move of the side to move written as `san`. Check marks, annotation suffixes like
+    // "!?", "e.p.", capture marks and the '=' of a promotion are optional, castling may be
+    // written with zeros and pieces may be over-qualified, like "Ng1f3". A piece move missing
+    // the qualifier it needs is reported as UnDisambiguable.
+    pub fn from_san(&self, san: &str) -> Result<Move, ChessError> {
+        let invalid = || ChessError::InvalidSan(san.to_string());
+        let suffixes = |c| "+#!?".contains(c);
+        let wanted = san.trim_end_matches(suffixes);
+        let wanted = wanted.strip_suffix("e.p.").map_or(wanted, str::trim_end);
+        let wanted = wanted.trim_end_matches(suffixes).replace('0', "O");
+        if wanted == "O-O" || wanted == "O-O-O" {
+            let king_side = wanted == "O-O";
+            return self
+                .legal_moves()
+                .into_iter()
+                .find(|m| {
+                    m.is_castling()
+                        && self
+                            .get_piece_data_at_square(&m.from())
+                            .and_then(|king| self.get_castling_side(king, &m.to()))
+                            == Some(king_side)
+                })
+                .ok_or_else(invalid);
+        }
+
+        let (piece, body) = match wanted.chars().next() {
+            Some(c) if c.is_ascii_uppercase() => {
+                (Self::char_to_piece(c).ok_or_else(invalid)?.0, &wanted[1..])
+            }
+            _ => (PieceKind::Pawn, wanted.as_str()),
+        };
+        // A trailing piece letter is a promotion, like "b8Q" or "b8=Q"
+        let (body, promoted_to) = match body.chars().last() {
+            Some(c) if c.is_ascii_uppercase() => (
+                body[..body.len() - 1].trim_end_matches('='),
+                Some(Self::char_to_piece(c).ok_or_else(invalid)?.0),
+            ),
+            _ => (body, None),
+        };
+        let body = body.replace('x', "");
+        let split = body.len().checked_sub(2).ok_or_else(invalid)?;
+        let to = body
+            .get(split..)
+            .and_then(square_index::parse_square)
+            .ok_or_else(invalid)?;
+
+        // Whatever precedes the destination narrows down the file and rank of the mover
+        let (mut file, mut rank) = (None, None);
+        for c in body[..split].chars() {
+            match c {
+                'a'..='h' if file.is_none() && rank.is_none() => {
+                    file = square_index::file_at(c as i32 - 'a' as i32)
+                }
+                '1'..='8' if rank.is_none() => rank = square_index::rank_at(c as i32 - '1' as i32),
+                _ => return Err(invalid()),
+            }
+        }
+        let from = self
+            .disambiguate_from_square(piece, self.side_to_move, &Square::new(file, rank), &to)
+            .map_err(|e| match e {
+                ChessError::UnDisambiguable => e,
+                _ => invalid(),
+            })?;
+        self.legal_moves()
+            .into_iter()
+            .find(|m| {
+                m.from() == from && m.to() == to && m.promotion() == promoted_to && !m.is_castling()
+            })
+            .ok_or_else(invalid)
+    }
+
+    // The shortest 'from' qualifier telling this piece apart from others of the same type
+    // that could also legally move to `to`
+    fn get_disambiguation(
+        &self,
+        piece: PieceKind,
+        color: Color,
+        from: &Square,
+        to: &Square,
+    ) -> String {
+        let (from_file, from_rank) = square_index::square_coords(from).unwrap();
+        let rivals: Vec<(i32, i32)> = self
+            .get_all_live_piece_data_with_type(piece, color)
+            .into_iter()
+            .filter(|p| p.curr_square() != Some(from))
+            .filter(|p| p.behavior.get_valid_squares(p, self).contains(to))
+            .filter_map(|p| square_index::square_coords(p.curr_square().unwrap()))
+            .collect();
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|(file, _)| *file != from_file) {
+            Self::file_char(from_file).to_string()
+        } else if rivals.iter().all(|(_, rank)| *rank != from_rank) {
+            (from_rank + 1).to_string()
+        } else {
+            square_index::square_name(from).unwrap()
+        }
+    }
+
+    fn file_char(file: i32) -> char {
+        (b'a' + file as u8) as char
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, ChessError, PieceKind};
+    use chess_pgn_parser::Square;
+
+    #[test]
+    fn test_basic_san() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        assert_eq!(board.to_san(&Square::E2, &Square::E4, None)?, "e4");
+        assert_eq!(board.to_san(&Square::G1, &Square::F3, None)?, "Nf3");
+        board.add_pgn_moves("1. e4 d5")?;
+        assert_eq!(board.to_san(&Square::E4, &Square::D5, None)?, "exd5");
+        assert!(board.to_san(&Square::E4, &Square::E6, None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_disambiguation_san() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. Nf3 d5 2. d3 e5")?;
+        assert_eq!(board.to_san(&Square::B1, &Square::D2, None)?, "Nbd2");
+        assert_eq!(board.to_san(&Square::F3, &Square::D2, None)?, "Nfd2");
+
+        let board = Board::from_fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1")?;
+        assert_eq!(board.to_san(&Square::A1, &Square::A3, None)?, "R1a3");
+        assert_eq!(board.to_san(&Square::A5, &Square::A3, None)?, "R5a3");
+
+        let board = Board::from_fen("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1")?;
+        assert_eq!(board.to_san(&Square::A1, &Square::B2, None)?, "Qa1b2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_special_moves_san() -> Result<(), ChessError> {
+        let board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1")?;
+        assert_eq!(board.to_san(&Square::E1, &Square::G1, None)?, "O-O");
+        assert_eq!(board.to_san(&Square::E1, &Square::C1, None)?, "O-O-O");
+        assert_eq!(board.to_san(&Square::E5, &Square::D6, None)?, "exd6");
+        assert_eq!(
+            board.to_san(&Square::B7, &Square::A8, Some(PieceKind::Queen))?,
+            "bxa8=Q+"
+        );
+        assert_eq!(
+            board.to_san(&Square::B7, &Square::B8, Some(PieceKind::Knight))?,
+            "b8=N"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_san() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        assert_eq!(board.from_san("e4")?.to_string(), "e2e4");
+        assert_eq!(board.from_san("Nf3!?")?.to_string(), "g1f3");
+        assert_eq!(board.from_san("Ngf3")?.to_string(), "g1f3");
+        assert_eq!(board.from_san("Ng1f3")?.to_string(), "g1f3");
+        assert!(board.from_san("Nhf3").is_err());
+        assert!(board.from_san("e5").is_err());
+        assert!(board.from_san("Qxh7").is_err());
+        assert!(board.from_san("xyz").is_err());
+        board.add_pgn_moves("1. Nf3 d5 2. d3 e5")?;
+        assert!(matches!(
+            board.from_san("Nd2"),
+            Err(ChessError::UnDisambiguable)
+        ));
+        assert_eq!(board.from_san("Nbd2")?.to_string(), "b1d2");
+        board.add_pgn_moves("3. e4 c6")?;
+        assert_eq!(board.from_san("ed5")?.to_string(), "e4d5");
+        assert_eq!(board.from_san("exd5")?.to_string(), "e4d5");
+
+        let board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1")?;
+        assert_eq!(board.from_san("0-0-0")?.to_string(), "e1c1");
+        assert_eq!(board.from_san("O-O")?.to_string(), "e1g1");
+        assert_eq!(board.from_san("exd6")?.to_string(), "e5d6");
+        assert_eq!(board.from_san("exd6 e.p.")?.to_string(), "e5d6");
+        assert_eq!(board.from_san("exd6e.p.+")?.to_string(), "e5d6");
+        assert_eq!(board.from_san("bxa8=N")?.to_string(), "b7a8n");
+        assert_eq!(board.from_san("bxa8N")?.to_string(), "b7a8n");
+        assert_eq!(board.from_san("b8Q+")?.to_string(), "b7b8q");
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_and_mate_san() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        board.add_pgn_moves("1. f3 e5 2. g4")?;
+        assert_eq!(board.to_san(&Square::D8, &Square::H4, None)?, "Qh4#");
+
+        let mut board = Board::new();
+        board.add_pgn_moves("1. e4 e5 2. d4")?;
+        assert_eq!(board.to_san(&Square::F8, &Square::B4, None)?, "Bb4+");
+        Ok(())
+    }
+}
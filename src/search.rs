@@ -0,0 +1,437 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::evaluation::PIECE_VALUES;
+use crate::{Board, Move, PieceKind};
+
+// Scores are in centipawns from the point of view of the side to move. Mate scores count
+// down from MATE_SCORE by the number of plies to the mate.
+pub const MATE_SCORE: i32 = 100_000;
+
+// Deepest iteration searched when no depth limit is given
+const MAX_DEPTH: u32 = 64;
+
+// The clock and stop flag are checked once every this many nodes
+const CHECK_INTERVAL: u64 = 1024;
+
+// When to stop searching. With no limits at all the search runs until `stop` is set or
+// MAX_DEPTH is reached.
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+    // Set from another thread to end the search early
+    pub stop: Arc<AtomicBool>,
+}
+
+// The outcome of the deepest completed iteration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    // The line of play expected to follow, starting with the best move
+    pub pv: Vec<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+// Beyond any mate score, for the initial search window
+const INFINITY: i32 = MATE_SCORE + 1;
+
+// Number of transposition table entries
+const TABLE_SIZE: usize = 1 << 16;
+
+// How a stored score relates to the position's real score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    // The real score is at least this, the search having stopped at a refutation
+    Lower,
+    // The real score is at most this, no move having reached alpha
+    Upper,
+}
+
+#[derive(Debug, Clone)]
+struct TableEntry {
+    key: u64,
+    depth: u32,
+    score: i32,
+    bound: Bound,
+    best_move: Option<Move>,
+}
+
+// Positions already searched, indexed by Zobrist hash. A newer entry replaces an older one
+// in the same slot.
+struct TranspositionTable {
+    entries: Vec<Option<TableEntry>>,
+}
+
+impl TranspositionTable {
+    fn new() -> TranspositionTable {
+        TranspositionTable {
+            entries: vec![None; TABLE_SIZE],
+        }
+    }
+
+    fn probe(&self, key: u64) -> Option<&TableEntry> {
+        self.entries[key as usize % TABLE_SIZE]
+            .as_ref()
+            .filter(|entry| entry.key == key)
+    }
+
+    fn store(&mut self, entry: TableEntry) {
+        let index = entry.key as usize % TABLE_SIZE;
+        self.entries[index] = Some(entry);
+    }
+}
+
+fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_SCORE - 2 * MAX_DEPTH as i32
+}
+
+// Mate scores are stored counted from the position rather than from the root, as the same
+// position can be reached at different plies
+fn score_to_table(score: i32, ply: i32) -> i32 {
+    if is_mate_score(score) {
+        score + score.signum() * ply
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: i32) -> i32 {
+    if is_mate_score(score) {
+        score - score.signum() * ply
+    } else {
+        score
+    }
+}
+
+struct Searcher<'a> {
+    limits: &'a SearchLimits,
+    deadline: Option<Instant>,
+    nodes: u64,
+    aborted: bool,
+    table: TranspositionTable,
+}
+
+impl Searcher<'_> {
+    fn check_limits(&mut self) -> bool {
+        self.aborted = self.aborted
+            || self.limits.stop.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+        self.aborted
+    }
+
+    // Counts the node, returning true if the search has to stop
+    fn visit(&mut self) -> bool {
+        self.nodes += 1;
+        self.aborted || self.nodes.is_multiple_of(CHECK_INTERVAL) && self.check_limits()
+    }
+
+    // Scores the position searching `depth` plies deep, filling `pv` with the best line
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: i32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+        if self.visit() {
+            return 0;
+        }
+        if ply > 0 && (board.repetition_count() > 1 || board.halfmove_clock() >= 100) {
+            return 0;
+        }
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+        let key = board.hash();
+        let entry = self.table.probe(key);
+        let hash_move = entry.and_then(|entry| entry.best_move);
+        // The root is always searched so there is a move to answer with
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return Self::no_moves_score(board, ply);
+        }
+        order_moves(board, &mut moves, hash_move);
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut child_pv = Vec::new();
+        for m in moves {
+            board
+                .play_legal_move(&m)
+                .expect("generated moves are legal");
+            let score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            board.undo();
+            if self.aborted {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(m);
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(m);
+                pv.append(&mut child_pv);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table.store(TableEntry {
+            key,
+            depth,
+            score: score_to_table(best_score, ply),
+            bound,
+            best_move,
+        });
+        best_score
+    }
+
+    // Plays out captures and queen promotions until the position is quiet, so the static
+    // evaluation isn't taken halfway through an exchange. The side to move may stand pat.
+    fn quiescence(&mut self, board: &mut Board, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+        if self.visit() {
+            return 0;
+        }
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return Self::no_moves_score(board, ply);
+        }
+        let mut best_score = board.evaluate();
+        if best_score >= beta {
+            return best_score;
+        }
+        alpha = alpha.max(best_score);
+        moves.retain(|m| m.is_capture() || m.promotion() == Some(PieceKind::Queen));
+        order_moves(board, &mut moves, None);
+        for m in moves {
+            board
+                .play_legal_move(&m)
+                .expect("generated moves are legal");
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.undo();
+            if self.aborted {
+                return 0;
+            }
+            best_score = best_score.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best_score
+    }
+
+    // Checkmate or stalemate
+    fn no_moves_score(board: &Board, ply: i32) -> i32 {
+        if board.is_in_check(board.side_to_move()) {
+            ply - MATE_SCORE
+        } else {
+            0
+        }
+    }
+}
+
+// Most valuable victim, least valuable attacker: the higher the score the sooner the capture
+// is tried. None if the move isn't a capture.
+fn capture_score(board: &Board, m: &Move) -> Option<i32> {
+    if !m.is_capture() {
+        return None;
+    }
+    let attacker = board.get_piece_data_at_square(&m.from())?.behavior.kind();
+    let victim = if m.is_en_passant() {
+        PieceKind::Pawn
+    } else {
+        board.get_piece_data_at_square(&m.to())?.behavior.kind()
+    };
+    Some(PIECE_VALUES[victim.index()] * 10 - PIECE_VALUES[attacker.index()] / 10)
+}
+
+// Puts the move from the transposition table first, then captures and promotions, then the
+// quiet moves
+fn order_moves(board: &Board, moves: &mut [Move], hash_move: Option<Move>) {
+    moves.sort_by_cached_key(|m| {
+        let score = if Some(*m) == hash_move {
+            i32::MAX
+        } else {
+            capture_score(board, m).unwrap_or(0)
+                + m.promotion().map_or(0, |piece| PIECE_VALUES[piece.index()])
+        };
+        Reverse(score)
+    });
+}
+
+impl Board {
+    // Looks for the best move of the side to move with alpha-beta search, deepening one ply
+    // at a time until a limit is reached
+    pub fn search(&self, limits: &SearchLimits) -> SearchResult {
+        self.search_with(limits, |_| {})
+    }
+
+    // Like `search`, calling `report` with the result of every completed iteration
+    pub fn search_with<F: FnMut(&SearchResult)>(
+        &self,
+        limits: &SearchLimits,
+        mut report: F,
+    ) -> SearchResult {
+        let mut board = self.clone();
+        let mut searcher = Searcher {
+            limits,
+            deadline: limits.time.map(|time| Instant::now() + time),
+            nodes: 0,
+            aborted: false,
+            table: TranspositionTable::new(),
+        };
+        let mut result = SearchResult {
+            best_move: board.legal_moves().first().copied(),
+            pv: Vec::new(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+        };
+        let mut pv = Vec::new();
+        for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH) {
+            if searcher.check_limits() {
+                break;
+            }
+            // The previous iteration's best move is searched first, found in the table
+            let score = searcher.negamax(&mut board, depth, 0, -INFINITY, INFINITY, &mut pv);
+            if searcher.aborted || pv.is_empty() {
+                break;
+            }
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                pv: pv.clone(),
+                score,
+                depth,
+                nodes: searcher.nodes,
+            };
+            report(&result);
+            if score.abs() >= MATE_SCORE - depth as i32 {
+                break;
+            }
+        }
+        result.nodes = searcher.nodes;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use crate::{Board, ChessError, Color, SearchLimits, MATE_SCORE};
+
+    fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    #[test]
+    fn test_search_finds_mate() -> Result<(), ChessError> {
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")?;
+        let result = board.search(&depth(3));
+        assert_eq!(result.best_move, Some(board.from_uci("a1a8")?));
+        assert_eq!(result.score, MATE_SCORE - 1);
+        assert_eq!(result.depth, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_wins_material() -> Result<(), ChessError> {
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1")?;
+        let result = board.search(&depth(2));
+        assert_eq!(result.best_move, Some(board.from_uci("d1d5")?));
+        assert!(result.score > 400);
+
+        // Quiescence sees the pawn is defended
+        let board = Board::from_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1")?;
+        let result = board.search(&depth(1));
+        assert_ne!(result.best_move, Some(board.from_uci("d1d5")?));
+
+        // The king would take the pawn if it waited
+        let board = Board::from_fen("8/1P6/2k5/8/8/8/8/7K w - - 0 1")?;
+        let result = board.search(&depth(2));
+        assert_eq!(result.best_move, Some(board.from_uci("b7b8q")?));
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_principal_variation() -> Result<(), ChessError> {
+        // Mate in two: one rook cuts the king off and the other mates on the back rank
+        let board = Board::from_fen("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1")?;
+        let result = board.search(&depth(4));
+        assert_eq!(result.score, MATE_SCORE - 3);
+        assert_eq!(result.pv.len(), 3);
+        assert_eq!(result.best_move.as_ref(), result.pv.first());
+
+        // The line can be played out and ends in mate
+        let mut board = board;
+        for m in result.pv.iter() {
+            board.apply_move(m)?;
+        }
+        assert!(board.is_in_check(Color::Black));
+        assert!(board.legal_moves_uci().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_limits() -> Result<(), ChessError> {
+        let board = Board::new();
+        let mut depths = Vec::new();
+        let result = board.search_with(&depth(3), |result| depths.push(result.depth));
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(result.depth, 3);
+        assert!(result.best_move.is_some());
+
+        // A stopped search still answers with a legal move
+        let limits = SearchLimits::default();
+        limits.stop.store(true, Ordering::Relaxed);
+        let result = board.search(&limits);
+        assert_eq!(result.depth, 0);
+        assert!(result.best_move.is_some());
+
+        let limits = SearchLimits {
+            time: Some(Duration::from_millis(50)),
+            ..SearchLimits::default()
+        };
+        assert!(board.search(&limits).best_move.is_some());
+
+        let board = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")?;
+        assert_eq!(board.search(&depth(2)).best_move, None);
+        Ok(())
+    }
+}
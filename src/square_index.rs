@@ -0,0 +1,84 @@
+use std::convert::TryFrom;
+
+use chess_pgn_parser::{File, Rank, Square};
+
+pub fn file_index(file: File) -> i32 {
+    match file {
+        File::A => 0,
+        File::B => 1,
+        File::C => 2,
+        File::D => 3,
+        File::E => 4,
+        File::F => 5,
+        File::G => 6,
+        File::H => 7,
+    }
+}
+
+pub fn rank_index(rank: Rank) -> i32 {
+    match rank {
+        Rank::R1 => 0,
+        Rank::R2 => 1,
+        Rank::R3 => 2,
+        Rank::R4 => 3,
+        Rank::R5 => 4,
+        Rank::R6 => 5,
+        Rank::R7 => 6,
+        Rank::R8 => 7,
+    }
+}
+
+// Returns (file, rank) indices in 0..8, or None if the square is not fully known
+pub fn square_coords(square: &Square) -> Option<(i32, i32)> {
+    Some((file_index(square.file()?), rank_index(square.rank()?)))
+}
+
+const FILES: [File; 8] = [
+    File::A,
+    File::B,
+    File::C,
+    File::D,
+    File::E,
+    File::F,
+    File::G,
+    File::H,
+];
+
+pub fn file_at(file: i32) -> Option<File> {
+    FILES.get(usize::try_from(file).ok()?).copied()
+}
+
+const RANKS: [Rank; 8] = [
+    Rank::R1,
+    Rank::R2,
+    Rank::R3,
+    Rank::R4,
+    Rank::R5,
+    Rank::R6,
+    Rank::R7,
+    Rank::R8,
+];
+
+pub fn rank_at(rank: i32) -> Option<Rank> {
+    RANKS.get(usize::try_from(rank).ok()?).copied()
+}
+
+pub fn square_at(file: i32, rank: i32) -> Option<Square> {
+    Some(Square::new_known(file_at(file)?, rank_at(rank)?))
+}
+
+// Algebraic name of the square, like "e4"
+pub fn square_name(square: &Square) -> Option<String> {
+    let (file, rank) = square_coords(square)?;
+    Some(format!("{}{}", (b'a' + file as u8) as char, rank + 1))
+}
+
+pub fn parse_square(name: &str) -> Option<Square> {
+    let mut chars = name.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?;
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    square_at(file as i32 - 'a' as i32, rank as i32 - '1' as i32)
+}
@@ -0,0 +1,158 @@
+use chess_pgn_parser::Square;
+
+use crate::square_index;
+use crate::{Board, ChessError, Color, Move, PieceKind};
+
+impl Board {
+    // A move in UCI's long algebraic notation, like "e2e4" or "e7e8q". Castling is written
+    // as the king moving to its destination, or on Chess960 boards as the king taking its
+    // own rook. The move isn't checked for legality.
+    pub fn to_uci(
+        &self,
+        from: &Square,
+        to: &Square,
+        promoted_to: Option<PieceKind>,
+    ) -> Result<String, ChessError> {
+        let piece_data = self
+            .get_piece_data_at_square(from)
+            .ok_or_else(|| ChessError::EmptySquare(from.clone()))?;
+        let rank = from.rank().ok_or(ChessError::IncorrectMoveParameters)?;
+        let to = match self.get_castling_side(piece_data, to) {
+            Some(king_side) if self.chess960 => {
+                Square::new_known(self.castling_files.rook(king_side), rank)
+            }
+            Some(king_side) => Square::new_known(Self::get_castled_files(king_side).0, rank),
+            None => to.clone(),
+        };
+        Self::get_uci(from, &to, promoted_to).ok_or(ChessError::IncorrectMoveParameters)
+    }
+
+    // The legal move written as `uci`. Castling is recognised in either notation.
+    pub fn from_uci(&self, uci: &str) -> Result<Move, ChessError> {
+        let invalid = || ChessError::InvalidUci(uci.to_string());
+        let from = uci
+            .get(0..2)
+            .and_then(square_index::parse_square)
+            .ok_or_else(invalid)?;
+        let to = uci
+            .get(2..4)
+            .and_then(square_index::parse_square)
+            .ok_or_else(invalid)?;
+        let promoted_to = match uci.get(4..) {
+            Some("") => None,
+            Some("n") => Some(PieceKind::Knight),
+            Some("b") => Some(PieceKind::Bishop),
+            Some("r") => Some(PieceKind::Rook),
+            Some("q") => Some(PieceKind::Queen),
+            _ => return Err(invalid()),
+        };
+        let piece_data = self.get_piece_data_at_square(&from).ok_or_else(invalid)?;
+        let castling_side = self.get_castling_side(piece_data, &to);
+        self.legal_moves()
+            .into_iter()
+            .find(|m| {
+                m.from() == from
+                    && m.promotion() == promoted_to
+                    && (m.to() == to
+                        || castling_side.is_some()
+                            && self.get_castling_side(piece_data, &m.to()) == castling_side)
+            })
+            .ok_or_else(invalid)
+    }
+
+    // Plays a move given in UCI notation
+    pub fn apply_uci(&mut self, uci: &str) -> Result<(), ChessError> {
+        let m = self.from_uci(uci)?;
+        self.apply_move(&m)
+    }
+
+    // Every legal move of the side to move in UCI notation
+    pub fn legal_moves_uci(&self) -> Vec<String> {
+        self.legal_moves().iter().map(|m| m.to_string()).collect()
+    }
+
+    // Writes the squares as given, with no castling conversion
+    pub(crate) fn get_uci(
+        from: &Square,
+        to: &Square,
+        promoted_to: Option<PieceKind>,
+    ) -> Option<String> {
+        let mut uci = square_index::square_name(from)?;
+        uci.push_str(&square_index::square_name(to)?);
+        if let Some(promotion) = promoted_to {
+            uci.push(Self::piece_to_char(promotion, Color::Black));
+        }
+        Some(uci)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Board, ChessError, PieceKind, Square};
+
+    #[test]
+    fn test_to_uci() -> Result<(), ChessError> {
+        let board = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+        assert_eq!(board.to_uci(&Square::A1, &Square::A7, None)?, "a1a7");
+        assert_eq!(
+            board.to_uci(&Square::B7, &Square::A8, Some(PieceKind::Queen))?,
+            "b7a8q"
+        );
+        assert_eq!(board.to_uci(&Square::E1, &Square::G1, None)?, "e1g1");
+        assert_eq!(board.to_uci(&Square::E1, &Square::A1, None)?, "e1c1");
+        assert!(board.to_uci(&Square::E4, &Square::E5, None).is_err());
+
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1")?;
+        assert_eq!(board.to_uci(&Square::E1, &Square::G1, None)?, "e1h1");
+        assert_eq!(board.to_uci(&Square::E1, &Square::A1, None)?, "e1a1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_uci() -> Result<(), ChessError> {
+        let mut board = Board::new();
+        for uci in ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "g8f6", "e1g1"] {
+            board.apply_uci(uci)?;
+        }
+        assert_eq!(
+            board.to_fen(),
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4"
+        );
+        assert!(board.move_list().last().unwrap().castling);
+
+        // Castling as the king taking its rook works on standard boards too
+        board.apply_uci("f8c5")?;
+        board.apply_uci("d2d3")?;
+        board.apply_uci("e8h8")?;
+        assert_eq!(board.move_list().last().unwrap().to, Square::G8);
+
+        let mut board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1")?;
+        assert!(board.apply_uci("b7b8").is_err());
+        board.apply_uci("b7b8n")?;
+        assert_eq!(board.to_fen(), "1N2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        let board = Board::new();
+        for uci in ["", "e2", "e2e5", "e2e4q", "e2e4x", "i2i4", "e3e4", "e1g1"] {
+            assert!(
+                matches!(board.from_uci(uci), Err(ChessError::InvalidUci(_))),
+                "{}",
+                uci
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_legal_moves_uci() -> Result<(), ChessError> {
+        let moves = Board::new().legal_moves_uci();
+        assert_eq!(moves.len(), 20);
+        assert!(moves.contains(&"g1f3".to_string()));
+
+        let board = Board::from_fen("1r3kr1/pppppppp/8/8/8/8/PPPPPPPP/1R3KR1 w GBgb - 0 1")?;
+        let moves = board.legal_moves_uci();
+        assert!(moves.contains(&"f1g1".to_string()));
+        assert!(moves.contains(&"f1b1".to_string()));
+        assert!(!moves.contains(&"f1c1".to_string()));
+        Ok(())
+    }
+}